@@ -0,0 +1,336 @@
+use std::fmt;
+
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, Request},
+    middleware::Next,
+    response::Response,
+};
+use http::StatusCode;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::{info, warn};
+
+use crate::{
+    routes::TenantPath,
+    state::AppState,
+    store::api_keys::{ApiKeySummary, API_KEY_PREFIX},
+};
+
+/// Permissions a caller of our API can hold, checked per route
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Scope {
+    #[serde(rename = "devices:read")]
+    DevicesRead,
+    #[serde(rename = "credentials:read")]
+    CredentialsRead,
+    #[serde(rename = "credentials:write")]
+    CredentialsWrite,
+    /// Grants every other scope on the caller's tenants, plus managing API keys for callers that
+    /// aren't bound to a tenant
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::DevicesRead => write!(f, "devices:read"),
+            Scope::CredentialsRead => write!(f, "credentials:read"),
+            Scope::CredentialsWrite => write!(f, "credentials:write"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl Scope {
+    fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "devices:read" => Some(Scope::DevicesRead),
+            "credentials:read" => Some(Scope::CredentialsRead),
+            "credentials:write" => Some(Scope::CredentialsWrite),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// The authenticated caller of a request, available to handlers as a request extension
+#[derive(Clone, Debug)]
+pub struct Principal {
+    /// API key id or JWT subject
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    /// Tenant the caller is restricted to, `None` means the caller may act on every tenant
+    pub tenant_id: Option<String>,
+}
+
+impl Principal {
+    pub fn is_allowed(&self, scope: Scope, tenant_id: Option<&str>) -> bool {
+        let tenant_allowed = match (self.tenant_id.as_deref(), tenant_id) {
+            (Some(allowed), Some(requested)) => allowed == requested,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        // Admin doesn't lift the tenant restriction, only callers without a tenant act across tenants
+        tenant_allowed && (self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin))
+    }
+
+    /// Whether the caller may mint a key with `scopes` for `tenant_id`, nobody can hand out more
+    /// access than they hold themselves
+    pub fn may_grant(&self, scopes: &[Scope], tenant_id: Option<&str>) -> bool {
+        scopes
+            .iter()
+            .all(|scope| self.is_allowed(*scope, tenant_id))
+    }
+}
+
+impl From<ApiKeySummary> for Principal {
+    fn from(key: ApiKeySummary) -> Self {
+        Self {
+            subject: key.key_id,
+            scopes: key.scopes,
+            tenant_id: key.tenant_id,
+        }
+    }
+}
+
+/// Claims we expect in JWTs issued for our API
+#[derive(Serialize, Deserialize, Debug)]
+struct JwtClaims {
+    sub: String,
+    exp: usize,
+    /// Space separated scopes, the same format OAuth uses
+    scope: String,
+    tenant_id: Option<String>,
+}
+
+/// Ways of authenticating with our API other than stored API keys
+pub struct ApiAuth {
+    // HS256 secret for verifying JWTs, JWTs are rejected if this isn't configured
+    jwt_key: Option<DecodingKey>,
+    // Operator supplied admin key, used to mint the first API keys
+    bootstrap_admin_key_hash: Option<Vec<u8>>,
+}
+
+impl ApiAuth {
+    pub fn new(jwt_secret: Option<String>, bootstrap_admin_key: Option<String>) -> Self {
+        Self {
+            jwt_key: jwt_secret.map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            bootstrap_admin_key_hash: bootstrap_admin_key
+                .map(|key| Sha256::digest(key.as_bytes()).to_vec()),
+        }
+    }
+
+    fn verify_bootstrap_key(&self, presented: &str) -> Option<Principal> {
+        // Compare hashes in constant time so the comparison doesn't leak how much of the key matched
+        let expected = self.bootstrap_admin_key_hash.as_ref()?;
+        bool::from(
+            Sha256::digest(presented.as_bytes())
+                .as_slice()
+                .ct_eq(expected.as_slice()),
+        )
+        .then(|| Principal {
+            subject: "bootstrap-admin".to_string(),
+            scopes: vec![Scope::Admin],
+            tenant_id: None,
+        })
+    }
+
+    fn verify_jwt(&self, token: &str) -> Option<Principal> {
+        let key = self.jwt_key.as_ref()?;
+        let claims = decode::<JwtClaims>(token, key, &Validation::new(Algorithm::HS256))
+            .inspect_err(|e| warn!("Rejected JWT: {}", e))
+            .ok()?
+            .claims;
+        Some(Principal {
+            subject: claims.sub,
+            // Unknown scopes are ignored rather than rejecting the token, so new scopes can be rolled out gradually
+            scopes: claims
+                .scope
+                .split_whitespace()
+                .filter_map(Scope::parse)
+                .collect(),
+            tenant_id: claims.tenant_id,
+        })
+    }
+}
+
+fn presented_token<B>(request: &Request<B>) -> Option<&str> {
+    let headers = request.headers();
+    if let Some(key) = headers.get("x-api-key") {
+        return key.to_str().ok();
+    }
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Middleware authenticating every request with an API key or a JWT
+/// Either can be sent as `Authorization: Bearer <token>`, API keys can also be sent as `X-API-Key`
+pub async fn authenticate<B>(
+    State(state): State<AppState>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let token = presented_token(&request).ok_or(StatusCode::UNAUTHORIZED)?;
+    let principal = match state.api_auth.verify_bootstrap_key(token) {
+        Some(principal) => Some(principal),
+        None if token.starts_with(API_KEY_PREFIX) => {
+            state.api_keys.verify(token).await.map(Principal::from)
+        }
+        None => state.api_auth.verify_jwt(token),
+    }
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+/// Middleware rejecting requests whose principal doesn't hold `scope` for the tenant in the path
+/// Must run after `authenticate`
+pub async fn require_scope<B>(
+    State(scope): State<Scope>,
+    tenant: Option<Path<TenantPath>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let principal = request
+        .extensions()
+        .get::<Principal>()
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let tenant_id = tenant.as_ref().map(|t| t.tenant_id.as_str());
+    if !principal.is_allowed(scope, tenant_id) {
+        info!(
+            "Denied {} to {} for tenant {:?}",
+            scope, principal.subject, tenant_id
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    use super::{ApiAuth, JwtClaims, Principal, Scope};
+
+    fn principal(scopes: Vec<Scope>, tenant_id: Option<&str>) -> Principal {
+        Principal {
+            subject: "test".to_string(),
+            scopes,
+            tenant_id: tenant_id.map(str::to_string),
+        }
+    }
+
+    fn jwt(secret: &str, scope: &str, exp: usize) -> String {
+        encode(
+            &Header::default(),
+            &JwtClaims {
+                sub: "dashboard".to_string(),
+                exp,
+                scope: scope.to_string(),
+                tenant_id: Some("acme".to_string()),
+            },
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn scopes_checked_per_tenant() {
+        let reader = principal(vec![Scope::DevicesRead], Some("acme"));
+        assert!(reader.is_allowed(Scope::DevicesRead, Some("acme")));
+        assert!(!reader.is_allowed(Scope::DevicesRead, Some("other")));
+        assert!(!reader.is_allowed(Scope::CredentialsWrite, Some("acme")));
+        // Tenant bound keys can't use routes that aren't scoped to a tenant
+        assert!(!reader.is_allowed(Scope::DevicesRead, None));
+
+        let operator = principal(vec![Scope::DevicesRead], None);
+        assert!(operator.is_allowed(Scope::DevicesRead, Some("other")));
+        assert!(!operator.is_allowed(Scope::Admin, None));
+
+        let tenant_admin = principal(vec![Scope::Admin], Some("acme"));
+        assert!(tenant_admin.is_allowed(Scope::CredentialsWrite, Some("acme")));
+        assert!(!tenant_admin.is_allowed(Scope::CredentialsWrite, Some("other")));
+        assert!(!tenant_admin.is_allowed(Scope::Admin, None));
+
+        let admin = principal(vec![Scope::Admin], None);
+        assert!(admin.is_allowed(Scope::CredentialsWrite, Some("other")));
+        assert!(admin.is_allowed(Scope::Admin, None));
+    }
+
+    #[test]
+    fn keys_granted_within_own_access() {
+        let admin = principal(vec![Scope::Admin], None);
+        assert!(admin.may_grant(&[Scope::Admin], None));
+        assert!(admin.may_grant(&[Scope::DevicesRead], Some("acme")));
+
+        // Scopes the caller doesn't hold
+        let writer = principal(vec![Scope::CredentialsWrite], None);
+        assert!(writer.may_grant(&[Scope::CredentialsWrite], Some("acme")));
+        assert!(!writer.may_grant(&[Scope::CredentialsWrite, Scope::DevicesRead], Some("acme")));
+        assert!(!writer.may_grant(&[Scope::Admin], None));
+
+        // Tenant bound callers can only mint keys for their own tenant
+        let tenant_admin = principal(vec![Scope::Admin], Some("acme"));
+        assert!(tenant_admin.may_grant(&[Scope::DevicesRead], Some("acme")));
+        assert!(!tenant_admin.may_grant(&[Scope::DevicesRead], Some("other")));
+        assert!(!tenant_admin.may_grant(&[Scope::DevicesRead], None));
+    }
+
+    #[test]
+    fn scope_names_round_trip() {
+        for scope in [
+            Scope::DevicesRead,
+            Scope::CredentialsRead,
+            Scope::CredentialsWrite,
+            Scope::Admin,
+        ] {
+            assert_eq!(Scope::parse(&scope.to_string()), Some(scope));
+            assert_eq!(
+                serde_json::to_string(&scope).unwrap(),
+                format!("\"{}\"", scope)
+            );
+        }
+    }
+
+    #[test]
+    fn verify_jwt() {
+        let auth = ApiAuth::new(Some("secret".to_string()), None);
+        let exp = (chrono::Utc::now().timestamp() + 60) as usize;
+
+        let principal = auth
+            .verify_jwt(&jwt("secret", "devices:read unknown:scope", exp))
+            .expect("Should be valid");
+        assert_eq!(principal.subject, "dashboard");
+        assert_eq!(principal.scopes, vec![Scope::DevicesRead]);
+        assert_eq!(principal.tenant_id.as_deref(), Some("acme"));
+
+        assert!(auth
+            .verify_jwt(&jwt("wrong secret", "devices:read", exp))
+            .is_none());
+        assert!(auth.verify_jwt(&jwt("secret", "devices:read", 1)).is_none());
+        assert!(ApiAuth::new(None, None)
+            .verify_jwt(&jwt("secret", "devices:read", exp))
+            .is_none());
+    }
+
+    #[test]
+    fn verify_bootstrap_key() {
+        let auth = ApiAuth::new(None, Some("bootstrap".to_string()));
+        let principal = auth
+            .verify_bootstrap_key("bootstrap")
+            .expect("Should match");
+        assert_eq!(principal.scopes, vec![Scope::Admin]);
+        assert!(auth.verify_bootstrap_key("other").is_none());
+        assert!(ApiAuth::new(None, None)
+            .verify_bootstrap_key("bootstrap")
+            .is_none());
+    }
+}
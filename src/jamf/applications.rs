@@ -0,0 +1,338 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::store::connections::JamfConnection;
+
+use super::{
+    client::JamfClientError, config::env_or, models::JamfComputer, provider::is_version_below,
+};
+
+/// How long a scan of every Mac's apps may take, and how long its result is searched before the
+/// next search scans again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApplicationSearchConfig {
+    pub timeout_secs: u64,
+    pub max_age_secs: u64,
+}
+
+impl Default for ApplicationSearchConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 300,
+            max_age_secs: 900,
+        }
+    }
+}
+
+impl ApplicationSearchConfig {
+    /// Read the limits from `JAMF_APPLICATION_SEARCH_TIMEOUT_SECS` and
+    /// `JAMF_APPLICATION_INDEX_MAX_AGE_SECS`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            timeout_secs: env_or("JAMF_APPLICATION_SEARCH_TIMEOUT_SECS", default.timeout_secs)
+                .max(1),
+            max_age_secs: env_or("JAMF_APPLICATION_INDEX_MAX_AGE_SECS", default.max_age_secs),
+        }
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub(crate) fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_secs)
+    }
+}
+
+/// Which Macs have an app installed, optionally only those with a version below `below_version`
+#[derive(Debug, Clone)]
+pub(crate) struct ApplicationQuery {
+    pub(crate) bundle_id: String,
+    pub(crate) below_version: Option<String>,
+}
+
+impl ApplicationQuery {
+    fn matches(&self, bundle_id: Option<&str>, version: Option<&str>) -> bool {
+        // Bundle ids aren't case sensitive, Jamf reports whatever the app's Info.plist says
+        if !bundle_id.is_some_and(|b| b.eq_ignore_ascii_case(&self.bundle_id)) {
+            return false;
+        }
+        match &self.below_version {
+            None => true,
+            // NOTE: A version we can't compare isn't counted as outdated, e.g. an empty version
+            Some(below_version) => version
+                .and_then(|v| is_version_below(v, below_version))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// An installed copy of the app, a Mac can have more than one
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ApplicationMatch {
+    device_id: Option<String>,
+    device_name: Option<String>,
+    name: Option<String>,
+    bundle_id: Option<String>,
+    version: Option<String>,
+    path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ApplicationsOutput {
+    applications: Vec<ApplicationMatch>,
+    /// Every Mac that was looked at, including the ones without the app
+    computers_scanned: usize,
+    /// When the apps were fetched from Jamf, searches within the max age reuse the same scan
+    fetched_at: DateTime<Utc>,
+}
+
+/// Every app installed on every Mac of a connection, as of one scan
+#[derive(Debug)]
+pub(crate) struct ApplicationInventory {
+    applications: Vec<ApplicationMatch>,
+    computers_scanned: usize,
+    fetched_at: DateTime<Utc>,
+}
+
+impl Default for ApplicationInventory {
+    fn default() -> Self {
+        Self {
+            applications: vec![],
+            computers_scanned: 0,
+            fetched_at: Utc::now(),
+        }
+    }
+}
+
+impl ApplicationInventory {
+    /// Add the apps installed on a computer
+    pub(crate) fn add(&mut self, computer: JamfComputer) {
+        self.computers_scanned += 1;
+        let device_name = computer.general.map(|g| g.name);
+        for application in computer.applications.unwrap_or_default() {
+            self.applications.push(ApplicationMatch {
+                device_id: computer.id.clone(),
+                device_name: device_name.clone(),
+                name: application.name,
+                bundle_id: application.bundle_id,
+                version: application.version,
+                path: application.path,
+            });
+        }
+    }
+
+    pub(crate) fn search(&self, query: &ApplicationQuery) -> ApplicationsOutput {
+        ApplicationsOutput {
+            applications: self
+                .applications
+                .iter()
+                .filter(|a| query.matches(a.bundle_id.as_deref(), a.version.as_deref()))
+                .cloned()
+                .collect(),
+            computers_scanned: self.computers_scanned,
+            fetched_at: self.fetched_at,
+        }
+    }
+
+    fn is_fresh(&self, max_age: Duration) -> bool {
+        chrono::Duration::from_std(max_age)
+            .ok()
+            .and_then(|max_age| self.fetched_at.checked_add_signed(max_age))
+            .is_some_and(|expires_at| Utc::now() < expires_at)
+    }
+}
+
+// Held while a connection's apps are being scanned, so searches that come in meanwhile wait for
+// that scan instead of starting their own
+type InventorySlot = Arc<tokio::sync::Mutex<Option<Arc<ApplicationInventory>>>>;
+
+/// The last app scan of each tenant connection, Jamf can't filter on apps so without it every
+/// search would fetch every Mac's apps again
+/// Kept in memory only, the first search after a restart scans again
+pub struct ApplicationIndex {
+    // NOTE: This is a std Mutex, it is never held across an await
+    entries: Mutex<HashMap<(String, String), InventorySlot>>,
+    max_age: Duration,
+}
+
+impl ApplicationIndex {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            entries: Mutex::default(),
+            max_age,
+        }
+    }
+
+    /// Search the connection's apps, scanning them with `scan` first unless a scan younger than the
+    /// max age is indexed
+    /// Only one scan runs per connection at a time, a failed scan leaves the index unchanged
+    pub(crate) async fn search<F, Fut>(
+        &self,
+        connection: &JamfConnection,
+        query: &ApplicationQuery,
+        scan: F,
+    ) -> Result<ApplicationsOutput, JamfClientError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ApplicationInventory, JamfClientError>>,
+    {
+        let slot = self
+            .entries
+            .lock()
+            .expect("Application index poisoned")
+            .entry((connection.tenant_id.clone(), connection.id.clone()))
+            .or_default()
+            .clone();
+        let mut indexed = slot.lock().await;
+        let inventory = match indexed.as_ref().filter(|i| i.is_fresh(self.max_age)) {
+            Some(inventory) => inventory.clone(),
+            None => {
+                let scanned = Arc::new(scan().await?);
+                *indexed = Some(scanned.clone());
+                scanned
+            }
+        };
+        drop(indexed);
+        Ok(inventory.search(query))
+    }
+
+    /// Forget the apps of a deleted connection
+    pub(crate) fn remove_connection(&self, tenant_id: &str, connection_id: &str) {
+        self.entries
+            .lock()
+            .expect("Application index poisoned")
+            .remove(&(tenant_id.to_string(), connection_id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use crate::{
+        jamf::{auth::JamfCredentials, models::JamfComputerInventoryResponse},
+        store::connections::JamfConnection,
+    };
+
+    use super::{ApplicationIndex, ApplicationInventory, ApplicationQuery, ApplicationsOutput};
+
+    fn inventory() -> ApplicationInventory {
+        let response: JamfComputerInventoryResponse =
+            serde_json::from_str(include_str!("testdata/computers_inventory.json")).unwrap();
+        let mut inventory = ApplicationInventory::default();
+        for computer in response.results {
+            inventory.add(computer);
+        }
+        inventory
+    }
+
+    fn query(bundle_id: &str, below_version: Option<&str>) -> ApplicationQuery {
+        ApplicationQuery {
+            bundle_id: bundle_id.to_string(),
+            below_version: below_version.map(str::to_string),
+        }
+    }
+
+    fn search(bundle_id: &str, below_version: Option<&str>) -> ApplicationsOutput {
+        inventory().search(&query(bundle_id, below_version))
+    }
+
+    fn connection(id: &str) -> JamfConnection {
+        JamfConnection {
+            id: id.to_string(),
+            tenant_id: "acme".to_string(),
+            jamf_url: "https://acme.jamfcloud.com".to_string(),
+            transport: Default::default(),
+            credentials: JamfCredentials::Basic {
+                username: "user".to_string(),
+                password: "pass".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn applications_below_version() {
+        let output = search("com.tinyspeck.slackmacgap", None);
+        assert_eq!(output.computers_scanned, 2);
+        assert_eq!(output.applications.len(), 1);
+        assert_eq!(output.applications[0].device_id.as_deref(), Some("12"));
+        assert_eq!(
+            output.applications[0].device_name.as_deref(),
+            Some("peter’s MacBook Air")
+        );
+
+        // Compared as versions, not as strings
+        assert_eq!(
+            search("COM.TINYSPECK.SLACKMACGAP", Some("4.100"))
+                .applications
+                .len(),
+            1
+        );
+        assert!(search("com.tinyspeck.slackmacgap", Some("4.4"))
+            .applications
+            .is_empty());
+        assert!(search("com.example.missing", None).applications.is_empty());
+    }
+
+    #[tokio::test]
+    async fn one_scan_per_connection() {
+        let index = ApplicationIndex::new(Duration::from_secs(900));
+        let scans = AtomicUsize::new(0);
+        let scan = || async {
+            scans.fetch_add(1, Ordering::SeqCst);
+            // Long enough for the other searches to queue up behind this scan
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(inventory())
+        };
+        let acme = connection("c1");
+        let slack = query("com.tinyspeck.slackmacgap", None);
+        let chrome = query("com.google.Chrome", None);
+        let (slack, chrome) = tokio::join!(
+            index.search(&acme, &slack, scan),
+            index.search(&acme, &chrome, scan),
+        );
+        assert_eq!(slack.unwrap().applications.len(), 1);
+        assert!(!chrome.unwrap().applications.is_empty());
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+
+        // Other connections and deleted ones are scanned again
+        index
+            .search(&connection("c2"), &query("com.example.missing", None), scan)
+            .await
+            .unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 2);
+        index.remove_connection("acme", "c1");
+        index
+            .search(&acme, &query("com.example.missing", None), scan)
+            .await
+            .unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn outdated_scan_replaced() {
+        let index = ApplicationIndex::new(Duration::ZERO);
+        let scans = AtomicUsize::new(0);
+        let scan = || async {
+            scans.fetch_add(1, Ordering::SeqCst);
+            Ok(inventory())
+        };
+        let acme = connection("c1");
+        let slack = query("com.tinyspeck.slackmacgap", None);
+        index.search(&acme, &slack, scan).await.unwrap();
+        index.search(&acme, &slack, scan).await.unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use chrono::{DateTime, Duration, Utc};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use super::{
+    client::{check_status, decode_json, JamfClientError},
+    models::{JamfAuthReponse, JamfOAuthResponse},
+    rate_limit::RateLimiter,
+};
+
+// Jamf tokens are short lived (20-30 minutes), renew them with keep-alive once they get this close to expiring
+static REFRESH_WINDOW_SECS: i64 = 5 * 60;
+
+// Treat tokens as expired slightly early so a request never goes out with a token that dies in flight
+static EXPIRY_SKEW_SECS: i64 = 30;
+
+/// How we authenticate with a Jamf instance
+/// NOTE: Serialize is only here so the credential store can encrypt credentials, never return these from a route
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum JamfCredentials {
+    /// Username and password of a Jamf user account, exchanged at `/api/v1/auth/token`
+    Basic { username: String, password: String },
+    /// API client created under API Roles & Clients, exchanged at `/api/oauth/token`
+    OAuthClientCredentials {
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+// Never print secrets, credentials end up in tracing spans
+impl fmt::Debug for JamfCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JamfCredentials::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            JamfCredentials::OAuthClientCredentials { client_id, .. } => f
+                .debug_struct("OAuthClientCredentials")
+                .field("client_id", client_id)
+                .field("client_secret", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+impl JamfCredentials {
+    /// The account or API client the credentials belong to
+    pub(crate) fn principal(&self) -> &str {
+        match self {
+            JamfCredentials::Basic { username, .. } => username,
+            JamfCredentials::OAuthClientCredentials { client_id, .. } => client_id,
+        }
+    }
+
+    pub(crate) fn auth_type(&self) -> &'static str {
+        match self {
+            JamfCredentials::Basic { .. } => "basic",
+            JamfCredentials::OAuthClientCredentials { .. } => "oauth_client_credentials",
+        }
+    }
+
+    /// Only tokens issued to user accounts can be extended, OAuth tokens have to be requested again
+    fn supports_keep_alive(&self) -> bool {
+        matches!(self, JamfCredentials::Basic { .. })
+    }
+
+    async fn request_token(
+        &self,
+        http: &Client,
+        jamf_url: &str,
+    ) -> Result<JamfToken, JamfClientError> {
+        match self {
+            JamfCredentials::Basic { username, password } => {
+                let response = http
+                    .post(format!("{}/api/v1/auth/token", jamf_url))
+                    .basic_auth(username, Some(password))
+                    .send()
+                    .await?;
+                decode_json::<JamfAuthReponse>("/api/v1/auth/token", response)
+                    .await?
+                    .try_into()
+            }
+            JamfCredentials::OAuthClientCredentials {
+                client_id,
+                client_secret,
+            } => {
+                let response = http
+                    .post(format!("{}/api/oauth/token", jamf_url))
+                    .form(&[
+                        ("grant_type", "client_credentials"),
+                        ("client_id", client_id),
+                        ("client_secret", client_secret),
+                    ])
+                    .send()
+                    .await?;
+                match decode_json::<JamfOAuthResponse>("/api/oauth/token", response).await {
+                    Ok(response) => Ok(response.into()),
+                    // Unknown clients and wrong secrets are a 400 invalid_client, not a 401
+                    Err(JamfClientError::UnexpectedStatus { path, status })
+                        if status == StatusCode::BAD_REQUEST =>
+                    {
+                        Err(JamfClientError::AuthenticationFailed { path })
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct JamfToken {
+    pub(crate) token: String,
+    pub(crate) expires: DateTime<Utc>,
+}
+
+impl TryFrom<JamfAuthReponse> for JamfToken {
+    type Error = JamfClientError;
+
+    fn try_from(response: JamfAuthReponse) -> Result<Self, Self::Error> {
+        Ok(Self {
+            token: response.token,
+            expires: response.expires.parse::<DateTime<Utc>>()?,
+        })
+    }
+}
+
+impl From<JamfOAuthResponse> for JamfToken {
+    fn from(response: JamfOAuthResponse) -> Self {
+        // OAuth responses carry a lifetime in seconds rather than an expiry timestamp
+        Self {
+            token: response.access_token,
+            expires: Utc::now() + Duration::seconds(response.expires_in),
+        }
+    }
+}
+
+impl JamfToken {
+    /// The token can no longer be used and we have to authenticate again
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires - Duration::seconds(EXPIRY_SKEW_SECS) <= now
+    }
+
+    /// The token is still valid but close enough to expiring that it should be renewed
+    fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires - Duration::seconds(REFRESH_WINDOW_SECS) <= now
+    }
+}
+
+/// Caches the bearer token for a single Jamf connection, renewing it before it expires
+pub(crate) struct TokenManager {
+    jamf_url: String,
+    credentials: JamfCredentials,
+    // The connection's transport, so authentication goes through the same proxy and TLS settings
+    http: Client,
+    // NOTE: This is a tokio Mutex on purpose, it is held while authenticating so concurrent requests
+    //  wait for a single refresh instead of all hitting the auth endpoint at once
+    token: Mutex<Option<JamfToken>>,
+    // Set once the connection was deleted or rotated away, a revoked manager never authenticates again
+    revoked: AtomicBool,
+    // The Jamf instance's budget, authenticating counts against it like any other request
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl TokenManager {
+    pub(crate) fn new(
+        jamf_url: String,
+        credentials: JamfCredentials,
+        http: Client,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Self {
+        Self {
+            jamf_url,
+            credentials,
+            http,
+            token: Mutex::new(None),
+            revoked: AtomicBool::new(false),
+            rate_limiter,
+        }
+    }
+
+    /// A new manager for the same connection that authenticates within `rate_limiter`
+    pub(crate) fn with_rate_limiter(&self, rate_limiter: Arc<RateLimiter>) -> Self {
+        Self::new(
+            self.jamf_url.clone(),
+            self.credentials.clone(),
+            self.http.clone(),
+            rate_limiter,
+        )
+    }
+
+    /// Get a valid bearer token, renewing or re-authenticating with Jamf only when required
+    pub(crate) async fn bearer_token(&self) -> Result<String, JamfClientError> {
+        let mut cached = self.token.lock().await;
+        if self.revoked.load(Ordering::SeqCst) {
+            return Err(JamfClientError::Revoked);
+        }
+        let now = Utc::now();
+        if let Some(token) = cached.as_ref() {
+            if !token.needs_refresh(now) {
+                return Ok(token.token.clone());
+            }
+            if self.credentials.supports_keep_alive() && !token.is_expired(now) {
+                match self.keep_alive(&token.token).await {
+                    Ok(renewed) => {
+                        let bearer_token = renewed.token.clone();
+                        *cached = Some(renewed);
+                        return Ok(bearer_token);
+                    }
+                    Err(e) => warn!("Failed to renew Jamf token, re-authenticating: {}", e),
+                }
+            }
+        }
+
+        let token = self.authenticate().await?;
+        let bearer_token = token.token.clone();
+        *cached = Some(token);
+        Ok(bearer_token)
+    }
+
+    /// Drop a token Jamf rejected so the next call to `bearer_token` authenticates again
+    /// If another request already replaced the token this does nothing
+    pub(crate) async fn invalidate(&self, rejected_token: &str) {
+        let mut cached = self.token.lock().await;
+        if cached
+            .as_ref()
+            .is_some_and(|token| token.token == rejected_token)
+        {
+            *cached = None;
+        }
+    }
+
+    /// Stop authenticating without telling Jamf, for a manager that never got a token
+    pub(crate) fn mark_revoked(&self) {
+        self.revoked.store(true, Ordering::SeqCst);
+    }
+
+    /// Invalidate the cached token with Jamf and stop authenticating, requests still using this
+    /// manager fail from now on
+    pub(crate) async fn revoke(&self) -> Result<(), JamfClientError> {
+        // The lock is held until Jamf confirms, so no request can pick up the token in the meantime
+        let mut cached = self.token.lock().await;
+        self.revoked.store(true, Ordering::SeqCst);
+        let Some(token) = cached.take() else {
+            return Ok(());
+        };
+        if token.is_expired(Utc::now()) {
+            return Ok(());
+        }
+        info!("Invalidating Jamf token for {}", self.jamf_url);
+        let _permit = self.rate_limiter.acquire().await;
+        let response = self
+            .http
+            .post(format!("{}/api/v1/auth/invalidate-token", self.jamf_url))
+            .bearer_auth(&token.token)
+            .send()
+            .await?;
+        check_status("/api/v1/auth/invalidate-token", response)?;
+        Ok(())
+    }
+
+    async fn authenticate(&self) -> Result<JamfToken, JamfClientError> {
+        info!(
+            "Authenticating with Jamf at {} as {}",
+            self.jamf_url,
+            self.credentials.principal()
+        );
+        let _permit = self.rate_limiter.acquire().await;
+        self.credentials
+            .request_token(&self.http, &self.jamf_url)
+            .await
+    }
+
+    async fn keep_alive(&self, token: &str) -> Result<JamfToken, JamfClientError> {
+        let _permit = self.rate_limiter.acquire().await;
+        let response = self
+            .http
+            .post(format!("{}/api/v1/auth/keep-alive", self.jamf_url))
+            .bearer_auth(token)
+            .send()
+            .await?;
+        decode_json::<JamfAuthReponse>("/api/v1/auth/keep-alive", response)
+            .await?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::jamf::models::{JamfAuthReponse, JamfOAuthResponse};
+
+    use crate::jamf::client::JamfClientError;
+
+    use std::sync::Arc;
+
+    use crate::jamf::rate_limit::{RateLimitConfig, RateLimiter};
+
+    use super::{JamfCredentials, JamfToken, TokenManager};
+
+    fn basic(username: &str, password: &str) -> JamfCredentials {
+        JamfCredentials::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_jamf_expiry() {
+        let token = JamfToken::try_from(JamfAuthReponse {
+            token: "token".to_string(),
+            expires: "2025-03-07T18:26:19.517Z".to_string(),
+        })
+        .expect("Should parse");
+        assert_eq!(
+            token.expires,
+            Utc.with_ymd_and_hms(2025, 3, 7, 18, 26, 19).unwrap() + Duration::milliseconds(517)
+        );
+    }
+
+    #[test]
+    fn parse_invalid_expiry() {
+        let token = JamfToken::try_from(JamfAuthReponse {
+            token: "token".to_string(),
+            expires: "tomorrow".to_string(),
+        });
+        assert!(token.is_err());
+    }
+
+    #[test]
+    fn oauth_token_expiry() {
+        let before = Utc::now();
+        let token = JamfToken::from(JamfOAuthResponse {
+            access_token: "token".to_string(),
+            token_type: "Bearer".to_string(),
+            scope: None,
+            expires_in: 1199,
+        });
+        assert_eq!(token.token, "token");
+        assert!(token.expires >= before + Duration::seconds(1199));
+        assert!(token.expires <= Utc::now() + Duration::seconds(1199));
+    }
+
+    #[test]
+    fn credentials_debug_redacts_secrets() {
+        let oauth = JamfCredentials::OAuthClientCredentials {
+            client_id: "client".to_string(),
+            client_secret: "super-secret".to_string(),
+        };
+        let formatted = format!("{:?} {:?}", basic("user", "hunter2"), oauth);
+        assert!(formatted.contains("user"));
+        assert!(formatted.contains("client"));
+        assert!(!formatted.contains("hunter2"));
+        assert!(!formatted.contains("super-secret"));
+    }
+
+    #[test]
+    fn token_refresh_window() {
+        let now = Utc::now();
+        let token = |expires_in| JamfToken {
+            token: "token".to_string(),
+            expires: now + expires_in,
+        };
+
+        let fresh = token(Duration::minutes(20));
+        assert!(!fresh.needs_refresh(now));
+        assert!(!fresh.is_expired(now));
+
+        let expiring = token(Duration::minutes(2));
+        assert!(expiring.needs_refresh(now));
+        assert!(!expiring.is_expired(now));
+
+        let expired = token(Duration::seconds(10));
+        assert!(expired.needs_refresh(now));
+        assert!(expired.is_expired(now));
+    }
+
+    #[tokio::test]
+    async fn revoked_manager_never_authenticates() {
+        // Nothing listens here, the test fails with a reqwest error if the manager tries to authenticate
+        let manager = TokenManager::new(
+            "http://127.0.0.1:9".to_string(),
+            basic("user", "pass"),
+            reqwest::Client::new(),
+            Arc::new(RateLimiter::new(Default::default())),
+        );
+        manager
+            .revoke()
+            .await
+            .expect("Nothing cached to invalidate");
+        assert!(matches!(
+            manager.bearer_token().await,
+            Err(JamfClientError::Revoked)
+        ));
+    }
+
+    #[tokio::test]
+    async fn authentication_waits_for_rate_limiter() {
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig::new(100, 10, 1)));
+        // Nothing listens here either, without a permit the manager would fail right away
+        let manager = TokenManager::new(
+            "http://127.0.0.1:9".to_string(),
+            basic("user", "pass"),
+            reqwest::Client::new(),
+            rate_limiter.clone(),
+        );
+        let permit = rate_limiter.acquire().await;
+        assert!(tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            manager.bearer_token()
+        )
+        .await
+        .is_err());
+        drop(permit);
+        assert!(matches!(
+            manager.bearer_token().await,
+            Err(JamfClientError::ReqwestError(_))
+        ));
+    }
+}
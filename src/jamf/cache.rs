@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::store::connections::JamfConnection;
+
+use super::{provider::DevicesOutput, query::ComputerInventoryQuery};
+
+// Every distinct filter gets its own entry, so the cache is bounded rather than growing with each new query
+static MAX_ENTRIES: usize = 256;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+struct CacheKey {
+    tenant_id: String,
+    connection_id: String,
+    query: Vec<(String, String)>,
+}
+
+impl CacheKey {
+    fn new(connection: &JamfConnection, query: &ComputerInventoryQuery) -> Self {
+        Self {
+            tenant_id: connection.tenant_id.clone(),
+            connection_id: connection.id.clone(),
+            query: query.params(),
+        }
+    }
+}
+
+struct CachedDevices {
+    output: DevicesOutput,
+    fetched_at: DateTime<Utc>,
+    // Insertion order, the entry with the lowest one is evicted first
+    sequence: u64,
+}
+
+/// The last device list we got from Jamf for each tenant connection and query, served while Jamf
+/// is down so dashboards keep showing something
+/// Kept in memory only, a restart while Jamf is down has nothing to fall back on
+pub struct DevicesCache {
+    // NOTE: This is a std Mutex, it is never held across an await
+    entries: Mutex<HashMap<CacheKey, CachedDevices>>,
+    next_sequence: AtomicU64,
+    max_entries: usize,
+}
+
+impl Default for DevicesCache {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::default(),
+            next_sequence: AtomicU64::new(0),
+            max_entries: MAX_ENTRIES,
+        }
+    }
+}
+
+impl DevicesCache {
+    /// Remember a successful fetch, evicting the oldest entry once the cache is full
+    pub(crate) fn insert(
+        &self,
+        connection: &JamfConnection,
+        query: &ComputerInventoryQuery,
+        output: &DevicesOutput,
+    ) {
+        let mut entries = self.entries.lock().expect("Devices cache poisoned");
+        let key = CacheKey::new(connection, query);
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.sequence)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            CachedDevices {
+                output: output.clone(),
+                fetched_at: Utc::now(),
+                sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            },
+        );
+    }
+
+    /// The last successful fetch for the same connection and query, marked as stale
+    pub(crate) fn get_stale(
+        &self,
+        connection: &JamfConnection,
+        query: &ComputerInventoryQuery,
+    ) -> Option<DevicesOutput> {
+        self.entries
+            .lock()
+            .expect("Devices cache poisoned")
+            .get(&CacheKey::new(connection, query))
+            .map(|cached| cached.output.clone().into_stale(cached.fetched_at))
+    }
+
+    /// Forget everything cached for a deleted connection
+    pub(crate) fn remove_connection(&self, tenant_id: &str, connection_id: &str) {
+        self.entries
+            .lock()
+            .expect("Devices cache poisoned")
+            .retain(|key, _| key.tenant_id != tenant_id || key.connection_id != connection_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        jamf::{
+            auth::JamfCredentials,
+            provider::DevicesOutput,
+            query::{ComputerField, ComputerInventoryQuery, Filter},
+        },
+        store::connections::JamfConnection,
+    };
+
+    use super::DevicesCache;
+
+    fn connection(tenant_id: &str, id: &str) -> JamfConnection {
+        JamfConnection {
+            id: id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            jamf_url: "https://acme.jamfcloud.com".to_string(),
+            transport: Default::default(),
+            credentials: JamfCredentials::Basic {
+                username: "user".to_string(),
+                password: "pass".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn stale_copy_per_connection_and_query() {
+        let cache = DevicesCache::default();
+        let acme = connection("acme", "c1");
+        let query = ComputerInventoryQuery::default();
+        assert!(cache.get_stale(&acme, &query).is_none());
+
+        cache.insert(&acme, &query, &DevicesOutput::default());
+        let stale = serde_json::to_value(cache.get_stale(&acme, &query).unwrap()).unwrap();
+        assert_eq!(stale["stale"], true);
+        assert!(stale["fetched_at"].is_string());
+
+        // Other queries and tenants never get this list
+        let filtered = query
+            .clone()
+            .filter(Filter::eq(ComputerField::GeneralName, "mac-*"));
+        assert!(cache.get_stale(&acme, &filtered).is_none());
+        assert!(cache
+            .get_stale(&connection("globex", "c1"), &query)
+            .is_none());
+
+        cache.remove_connection("acme", "c1");
+        assert!(cache.get_stale(&acme, &query).is_none());
+    }
+
+    #[test]
+    fn oldest_entry_evicted() {
+        let cache = DevicesCache {
+            max_entries: 2,
+            ..Default::default()
+        };
+        let connections = [
+            connection("acme", "c1"),
+            connection("acme", "c2"),
+            connection("acme", "c3"),
+        ];
+        let query = ComputerInventoryQuery::default();
+        for connection in &connections {
+            cache.insert(connection, &query, &DevicesOutput::default());
+        }
+        assert!(cache.get_stale(&connections[0], &query).is_none());
+        assert!(cache.get_stale(&connections[1], &query).is_some());
+        assert!(cache.get_stale(&connections[2], &query).is_some());
+    }
+}
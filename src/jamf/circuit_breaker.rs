@@ -0,0 +1,184 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+use tracing::warn;
+
+use super::{client::JamfClientError, config::env_or};
+
+/// When to stop calling a Jamf instance that keeps failing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Failed requests in a row before the circuit opens
+    pub failure_threshold: u32,
+    /// How long requests fail fast before a single trial request is let through
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Read the config from `JAMF_CIRCUIT_FAILURE_THRESHOLD` and `JAMF_CIRCUIT_OPEN_SECS`,
+    /// falling back to the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            failure_threshold: env_or("JAMF_CIRCUIT_FAILURE_THRESHOLD", default.failure_threshold)
+                .max(1),
+            open_duration: Duration::from_secs(env_or(
+                "JAMF_CIRCUIT_OPEN_SECS",
+                default.open_duration.as_secs(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    // A trial request is in flight, if its caller went away we let another one through after `open_duration`
+    HalfOpen { since: Instant },
+}
+
+/// Fails requests fast while Jamf is down, instead of every request waiting for its own timeout
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    // NOTE: This is a std Mutex, it is never held across an await
+    state: Mutex<CircuitState>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CircuitState::Closed { failures: 0 }),
+        }
+    }
+
+    /// Whether a request may be sent, otherwise how long until Jamf is tried again
+    pub(crate) fn check(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().expect("Circuit breaker poisoned");
+        let now = Instant::now();
+        match *state {
+            CircuitState::Closed { .. } => Ok(()),
+            CircuitState::Open { until } if now < until => Err(until - now),
+            CircuitState::HalfOpen { since } if now < since + self.config.open_duration => {
+                Err(since + self.config.open_duration - now)
+            }
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => {
+                *state = CircuitState::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    /// Record how a request let through by `check` went
+    /// Only failures that say Jamf is unavailable count, a 404 still means Jamf is up
+    pub(crate) fn record<T>(&self, result: &Result<T, JamfClientError>) {
+        let mut state = self.state.lock().expect("Circuit breaker poisoned");
+        let failed = matches!(result, Err(e) if e.is_unavailable());
+        *state = match (&*state, failed) {
+            (_, false) => CircuitState::Closed { failures: 0 },
+            (CircuitState::Closed { failures }, true)
+                if failures + 1 < self.config.failure_threshold =>
+            {
+                CircuitState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, true) => {
+                warn!(
+                    "Jamf keeps failing, failing requests fast for {:?}",
+                    self.config.open_duration
+                );
+                CircuitState::Open {
+                    until: Instant::now() + self.config.open_duration,
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+
+    use crate::jamf::client::JamfClientError;
+
+    use super::{CircuitBreaker, CircuitBreakerConfig};
+
+    fn server_error() -> Result<(), JamfClientError> {
+        Err(JamfClientError::ServerError {
+            path: "/api/v1/computers-inventory".to_string(),
+            status: StatusCode::BAD_GATEWAY,
+            retry_after: None,
+        })
+    }
+
+    fn breaker(open_duration: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            open_duration,
+        })
+    }
+
+    #[test]
+    fn opens_after_repeated_failures() {
+        let breaker = breaker(Duration::from_secs(30));
+        for _ in 0..2 {
+            breaker.check().unwrap();
+            breaker.record(&server_error());
+        }
+        // A success in between resets the count
+        breaker.record(&Ok(()));
+        for _ in 0..2 {
+            breaker.record(&server_error());
+        }
+        assert!(breaker.check().is_ok());
+        breaker.record(&server_error());
+        let retry_after = breaker.check().unwrap_err();
+        assert!(retry_after > Duration::from_secs(29));
+    }
+
+    #[test]
+    fn errors_from_a_healthy_jamf_ignored() {
+        let breaker = breaker(Duration::from_secs(30));
+        for _ in 0..5 {
+            breaker.record::<()>(&Err(JamfClientError::NotFound {
+                path: "/api/v1/computers-inventory-detail/1".to_string(),
+            }));
+        }
+        assert!(breaker.check().is_ok());
+    }
+
+    #[tokio::test]
+    async fn single_trial_after_open_duration() {
+        let breaker = breaker(Duration::from_millis(20));
+        for _ in 0..3 {
+            breaker.record(&server_error());
+        }
+        assert!(breaker.check().is_err());
+        tokio::time::sleep(Duration::from_millis(25)).await;
+
+        // Only one request gets to find out whether Jamf is back
+        assert!(breaker.check().is_ok());
+        assert!(breaker.check().is_err());
+        breaker.record(&server_error());
+        assert!(breaker.check().is_err());
+
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        assert!(breaker.check().is_ok());
+        breaker.record(&Ok(()));
+        assert!(breaker.check().is_ok());
+        assert!(breaker.check().is_ok());
+    }
+}
@@ -1,33 +1,130 @@
+use std::{fmt, future::Future, ops::Range, sync::Arc, time::Duration};
+
 use enum_dispatch::enum_dispatch;
-use reqwest::Client;
+use futures::{future, stream, stream::BoxStream, StreamExt, TryStreamExt};
+use reqwest::{header::RETRY_AFTER, Client, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::OnceCell;
-use tracing::error;
+use tokio::time::sleep;
+use tracing::{error, field, info, instrument, warn};
 
-use crate::jamf::models::JamfComputerInventoryResponse;
+use crate::jamf::models::{
+    JamfComputer, JamfComputerInventoryResponse, JamfInventoryResponse, JamfMobileDevice,
+    JamfMobileDeviceDetail, JamfMobileDeviceInventoryResponse,
+};
 
-use super::models::{JamfAuthReponse, JamfAvailableUpdates};
+use super::{
+    auth::{JamfCredentials, TokenManager},
+    circuit_breaker::CircuitBreaker,
+    config::JamfClientConfig,
+    models::{JamfAuthDetails, JamfAvailableUpdates, JamfProVersion},
+    query::{ComputerInventoryQuery, MobileDeviceInventoryQuery},
+    rate_limit::RateLimiter,
+};
 
-// We should only create a single reqwest::Client and keep it as a singleton to avoid having many open connections
-static JAMF_CLIENT: OnceCell<Client> = OnceCell::const_new();
+/// Fetch pages with at most `concurrency` requests in flight, the results keep the order of `pages`
+/// The first failed page fails the whole fetch, pages still in flight are dropped
+pub(crate) async fn fetch_pages<T, F, Fut>(
+    pages: Range<usize>,
+    concurrency: usize,
+    fetch_page: F,
+) -> Result<Vec<T>, JamfClientError>
+where
+    F: Fn(usize) -> Fut,
+    Fut: Future<Output = Result<T, JamfClientError>>,
+{
+    stream::iter(pages)
+        .map(fetch_page)
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
 
-static PAGE_SIZE: usize = 100;
+static COMPUTERS_INVENTORY_PATH: &str = "/api/v1/computers-inventory";
 
-#[derive(PartialEq, Eq, Debug)]
-/// Sections for computer inventory API 
-/// note: This enum is not exhaustive
+// Unlike `/api/v2/mobile-devices` this includes the OS version, so we don't need a request per device
+static MOBILE_DEVICES_INVENTORY_PATH: &str = "/api/v2/mobile-devices/detail";
+
+// How long a rotated client waits for in-flight requests before its token is invalidated regardless
+static REVOKE_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+/// Sections for computer inventory API, every section Jamf offers
+/// Sections that aren't requested are left out of the response
 pub(crate) enum ComputerInventorySection {
     General,
+    DiskEncryption,
+    Purchasing,
+    Applications,
+    Storage,
+    UserAndLocation,
+    ConfigurationProfiles,
+    Printers,
+    Services,
     Hardware,
+    LocalUserAccounts,
+    Certificates,
+    Attachments,
+    Plugins,
+    PackageReceipts,
+    Fonts,
+    Security,
     OperatingSystem,
+    LicensedSoftware,
+    Ibeacons,
+    SoftwareUpdates,
+    ExtensionAttributes,
+    ContentCaching,
+    GroupMemberships,
 }
 
-impl ToString for ComputerInventorySection {
-    fn to_string(&self) -> String {
+impl fmt::Display for ComputerInventorySection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ComputerInventorySection::General => "GENERAL".to_string(),
-            ComputerInventorySection::Hardware => "HARDWARE".to_string(),
-            ComputerInventorySection::OperatingSystem => "OPERATING_SYSTEM".to_string(),
+            ComputerInventorySection::General => write!(f, "GENERAL"),
+            ComputerInventorySection::DiskEncryption => write!(f, "DISK_ENCRYPTION"),
+            ComputerInventorySection::Purchasing => write!(f, "PURCHASING"),
+            ComputerInventorySection::Applications => write!(f, "APPLICATIONS"),
+            ComputerInventorySection::Storage => write!(f, "STORAGE"),
+            ComputerInventorySection::UserAndLocation => write!(f, "USER_AND_LOCATION"),
+            ComputerInventorySection::ConfigurationProfiles => write!(f, "CONFIGURATION_PROFILES"),
+            ComputerInventorySection::Printers => write!(f, "PRINTERS"),
+            ComputerInventorySection::Services => write!(f, "SERVICES"),
+            ComputerInventorySection::Hardware => write!(f, "HARDWARE"),
+            ComputerInventorySection::LocalUserAccounts => write!(f, "LOCAL_USER_ACCOUNTS"),
+            ComputerInventorySection::Certificates => write!(f, "CERTIFICATES"),
+            ComputerInventorySection::Attachments => write!(f, "ATTACHMENTS"),
+            ComputerInventorySection::Plugins => write!(f, "PLUGINS"),
+            ComputerInventorySection::PackageReceipts => write!(f, "PACKAGE_RECEIPTS"),
+            ComputerInventorySection::Fonts => write!(f, "FONTS"),
+            ComputerInventorySection::Security => write!(f, "SECURITY"),
+            ComputerInventorySection::OperatingSystem => write!(f, "OPERATING_SYSTEM"),
+            ComputerInventorySection::LicensedSoftware => write!(f, "LICENSED_SOFTWARE"),
+            ComputerInventorySection::Ibeacons => write!(f, "IBEACONS"),
+            ComputerInventorySection::SoftwareUpdates => write!(f, "SOFTWARE_UPDATES"),
+            ComputerInventorySection::ExtensionAttributes => write!(f, "EXTENSION_ATTRIBUTES"),
+            ComputerInventorySection::ContentCaching => write!(f, "CONTENT_CACHING"),
+            ComputerInventorySection::GroupMemberships => write!(f, "GROUP_MEMBERSHIPS"),
+        }
+    }
+}
+
+/// Sections for mobile device inventory API
+/// note: This enum is not exhaustive
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum MobileDeviceSection {
+    General,
+    Hardware,
+    UserAndLocation,
+}
+
+impl fmt::Display for MobileDeviceSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MobileDeviceSection::General => write!(f, "GENERAL"),
+            MobileDeviceSection::Hardware => write!(f, "HARDWARE"),
+            MobileDeviceSection::UserAndLocation => write!(f, "USER_AND_LOCATION"),
         }
     }
 }
@@ -35,14 +132,45 @@ impl ToString for ComputerInventorySection {
 #[enum_dispatch]
 #[mockall::automock]
 pub(crate) trait JamfClientTrait {
+    /// Fetch the computers matching `query`, filtering and sorting is done by Jamf
     async fn get_computer_inventory(
         &self,
-        section: Vec<ComputerInventorySection>,
+        query: ComputerInventoryQuery,
     ) -> Result<JamfComputerInventoryResponse, JamfClientError>;
 
+    /// Stream computers from computer inventory a page at a time, so the whole fleet is never held in memory
+    fn stream_computer_inventory(
+        &self,
+        query: ComputerInventoryQuery,
+    ) -> BoxStream<'static, Result<Vec<JamfComputer>, JamfClientError>>;
+
+    /// Fetch a single computer with every inventory section, `NotFound` if Jamf has no such computer
+    async fn get_computer_detail(&self, id: &str) -> Result<JamfComputer, JamfClientError>;
+
+    /// Fetch the mobile devices (iPhones, iPads and Apple TVs) matching `query`
+    async fn get_mobile_device_inventory(
+        &self,
+        query: MobileDeviceInventoryQuery,
+    ) -> Result<JamfMobileDeviceInventoryResponse, JamfClientError>;
+
+    /// Stream mobile devices a page at a time, like `stream_computer_inventory`
+    fn stream_mobile_device_inventory(
+        &self,
+        query: MobileDeviceInventoryQuery,
+    ) -> BoxStream<'static, Result<Vec<JamfMobileDevice>, JamfClientError>>;
+
+    /// Fetch a single mobile device, `NotFound` if Jamf has no such device
+    async fn get_mobile_device_detail(
+        &self,
+        id: &str,
+    ) -> Result<JamfMobileDeviceDetail, JamfClientError>;
+
     async fn get_os_managed_updates(&self) -> Result<JamfAvailableUpdates, JamfClientError>;
 
-    // TODO: There aren't any mobile devices on the test jamf instance, but if there were I would add a method for fetching them
+    async fn get_jamf_pro_version(&self) -> Result<JamfProVersion, JamfClientError>;
+
+    /// Details of the account we're authenticated as, including its privileges
+    async fn get_auth_details(&self) -> Result<JamfAuthDetails, JamfClientError>;
 }
 
 #[enum_dispatch(JamfClientTrait)]
@@ -52,140 +180,543 @@ pub(crate) enum JamfClient {
     Mock(MockJamfClientTrait),
 }
 
+#[derive(Clone)]
 pub(crate) struct JamfClientImpl {
     jamf_url: String,
-    bearer_token: String,
+    // Built from the connection's transport config, see `JamfClientRegistry::http_client`
+    http: Client,
+    token_manager: Arc<TokenManager>,
+    // Shared with every other client for the same Jamf instance, see `RateLimiters`
+    rate_limiter: Arc<RateLimiter>,
+    // Shared by clones, so every request for the connection sees the same failures
+    circuit_breaker: Arc<CircuitBreaker>,
+    config: JamfClientConfig,
 }
 
 #[derive(Error, Debug)]
 pub enum JamfClientError {
     #[error("Failed to reach Jampf with error {0}")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("Jamf returned an invalid token expiry: {0}")]
+    InvalidTokenExpiry(#[from] chrono::ParseError),
+    #[error("The Jamf connection was deleted or its credentials were rotated")]
+    Revoked,
+    #[error("Jamf rejected the credentials for {path}")]
+    AuthenticationFailed { path: String },
+    #[error("The Jamf account lacks the privileges for {path}")]
+    PermissionDenied { path: String },
+    #[error("Jamf has nothing at {path}")]
+    NotFound { path: String },
+    #[error("Jamf rate limited {path}")]
+    RateLimited {
+        path: String,
+        /// How long Jamf asked us to wait, from the Retry-After header
+        retry_after: Option<Duration>,
+    },
+    #[error("Jamf failed with {status} for {path}")]
+    ServerError {
+        path: String,
+        status: StatusCode,
+        /// Jamf sends Retry-After with 503s during maintenance
+        retry_after: Option<Duration>,
+    },
+    #[error("Jamf returned unexpected status {status} for {path}")]
+    UnexpectedStatus { path: String, status: StatusCode },
+    #[error("Jamf kept failing, not calling {path} for another {}s", .retry_after.as_secs())]
+    CircuitOpen {
+        path: String,
+        /// When the circuit breaker lets a request through again
+        retry_after: Duration,
+    },
+    #[error("Jamf returned a malformed payload for {path}: {error}, body starts with {snippet:?}")]
+    MalformedPayload {
+        path: String,
+        error: String,
+        /// Start of the body, enough to tell an HTML error page from truncated JSON
+        snippet: String,
+    },
 }
 
-// We should only create a single reqwest::Client and keep it as a singleton to avoid having many open connections
-async fn get_client() -> Client {
-    JAMF_CLIENT
-        .get_or_init(|| async { Client::new() })
-        .await
-        .clone()
+impl JamfClientError {
+    /// Whether Jamf is down or unreachable, as opposed to answering with something we can't use
+    pub(crate) fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            JamfClientError::ReqwestError(_)
+                | JamfClientError::ServerError { .. }
+                | JamfClientError::CircuitOpen { .. }
+        )
+    }
+}
+
+// Only the start of a malformed body is kept, Jamf error pages can be large
+static BODY_SNIPPET_LEN: usize = 200;
+
+/// Turn error statuses from Jamf into typed errors, successful responses are passed through
+pub(crate) fn check_status(path: &str, response: Response) -> Result<Response, JamfClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let path = path.to_string();
+    // NOTE: Retry-After can also be an HTTP date, Jamf only sends seconds
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    Err(match status {
+        StatusCode::UNAUTHORIZED => JamfClientError::AuthenticationFailed { path },
+        StatusCode::FORBIDDEN => JamfClientError::PermissionDenied { path },
+        StatusCode::NOT_FOUND => JamfClientError::NotFound { path },
+        StatusCode::TOO_MANY_REQUESTS => JamfClientError::RateLimited { path, retry_after },
+        status if status.is_server_error() => JamfClientError::ServerError {
+            path,
+            status,
+            retry_after,
+        },
+        status => JamfClientError::UnexpectedStatus { path, status },
+    })
+}
+
+/// Check the status and deserialize the body, keeping a snippet of the body if it isn't what we expected
+pub(crate) async fn decode_json<T: DeserializeOwned>(
+    path: &str,
+    response: Response,
+) -> Result<T, JamfClientError> {
+    let body = check_status(path, response)?.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| JamfClientError::MalformedPayload {
+        path: path.to_string(),
+        error: e.to_string(),
+        snippet: String::from_utf8_lossy(&body)
+            .chars()
+            .take(BODY_SNIPPET_LEN)
+            .collect(),
+    })
 }
 
 impl JamfClientImpl {
-    pub async fn new(
-        username: String,
-        password: String,
+    /// Create a client without authenticating, the first request will fetch a token
+    /// Clones share the same token cache, so keep clients around to avoid re-authenticating with Jamf
+    pub(crate) fn with_credentials(
+        credentials: JamfCredentials,
         jamf_url: String,
-    ) -> Result<Self, JamfClientError> {
-        // NOTE: In a real app the request would probably come with a bearer token
-        // just for the sake of this example, we authenticate when creating the client
-        // we never have to refresh the token because the JamfClientImpl only lives
-        // for a single request
-        let response = get_client()
-            .await
-            .post(format!("{}/api/v1/auth/token", jamf_url))
-            .basic_auth(username, Some(password))
-            .send()
-            .await?;
-        let jamf_response = response.json::<JamfAuthReponse>().await?;
-        Ok(Self {
+        http: Client,
+    ) -> Self {
+        let rate_limiter = Arc::new(RateLimiter::new(Default::default()));
+        Self {
+            token_manager: Arc::new(TokenManager::new(
+                jamf_url.clone(),
+                credentials,
+                http.clone(),
+                rate_limiter.clone(),
+            )),
             jamf_url,
-            bearer_token: jamf_response.token,
-        })
+            http,
+            rate_limiter,
+            circuit_breaker: Arc::new(CircuitBreaker::new(Default::default())),
+            config: JamfClientConfig::default(),
+        }
+    }
+
+    pub(crate) fn with_config(mut self, config: JamfClientConfig) -> Self {
+        self.circuit_breaker = Arc::new(CircuitBreaker::new(config.circuit_breaker.clone()));
+        self.config = config;
+        self
+    }
+
+    pub(crate) fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.token_manager = Arc::new(self.token_manager.with_rate_limiter(rate_limiter.clone()));
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Make sure we hold a valid token, fails if Jamf rejects the credentials
+    pub(crate) async fn authenticate(&self) -> Result<(), JamfClientError> {
+        self.token_manager.bearer_token().await?;
+        Ok(())
+    }
+
+    /// Invalidate the client's token with Jamf right away, requests still using it will fail
+    pub(crate) async fn revoke(&self) {
+        if let Err(e) = self.token_manager.revoke().await {
+            // The token expires on its own, so this only shortens its lifetime
+            warn!(
+                "Failed to invalidate Jamf token for {}: {}",
+                self.jamf_url, e
+            );
+        }
+    }
+
+    /// A client that fails every request as revoked, without ever authenticating
+    pub(crate) fn into_revoked(self) -> Self {
+        self.token_manager.mark_revoked();
+        self
+    }
+
+    /// Invalidate the client's token once no request uses it anymore, so syncs that started before
+    /// a credential rotation can finish with the old token
+    pub(crate) fn revoke_when_idle(self) {
+        tokio::spawn(async move {
+            let started = tokio::time::Instant::now();
+            // Every in-flight request holds a clone of the client, so only ours is left once they're done
+            while Arc::strong_count(&self.token_manager) > 1
+                && started.elapsed() < REVOKE_GRACE_PERIOD
+            {
+                sleep(Duration::from_secs(1)).await;
+            }
+            info!("Revoking rotated Jamf client for {}", self.jamf_url);
+            self.revoke().await;
+        });
+    }
+
+    #[cfg(test)]
+    pub(crate) fn shares_token_cache(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.token_manager, &other.token_manager)
+    }
+
+    async fn send_get(
+        &self,
+        path: &str,
+        query: &[(String, String)],
+        bearer_token: &str,
+    ) -> Result<Response, JamfClientError> {
+        Ok(self
+            .http
+            .get(format!("{}{}", self.jamf_url, path))
+            .header("accept", "application/json")
+            .bearer_auth(bearer_token)
+            .query(query)
+            .send()
+            .await?)
+    }
+
+    // Helper function to make an authenticated GET request
+    // Transient failures are retried according to the retry policy, GETs are safe to repeat
+    // Once Jamf failed too often in a row we fail fast until the circuit breaker lets a trial through
+    #[instrument(skip(self, query), fields(retries = field::Empty))]
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Result<T, JamfClientError> {
+        self.circuit_breaker
+            .check()
+            .map_err(|retry_after| JamfClientError::CircuitOpen {
+                path: path.to_string(),
+                retry_after,
+            })?;
+        let result = self
+            .config
+            .retry_policy
+            .retry(|| self.get_json_once(path, query))
+            .await;
+        self.circuit_breaker.record(&result);
+        result
+    }
+
+    // If Jamf rejects our token (e.g. it was invalidated server side) we re-authenticate and try once more
+    async fn get_json_once<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Result<T, JamfClientError> {
+        let bearer_token = self.token_manager.bearer_token().await?;
+        // Held until the body is read, so slow responses count against the concurrency cap
+        let mut permit = self.rate_limiter.acquire().await;
+        let mut response = self.send_get(path, query, &bearer_token).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            warn!("Jamf rejected bearer token for {}, re-authenticating", path);
+            // Authenticating needs a permit of its own, holding ours could leave no slot for it
+            drop(permit);
+            self.token_manager.invalidate(&bearer_token).await;
+            let bearer_token = self.token_manager.bearer_token().await?;
+            permit = self.rate_limiter.acquire().await;
+            response = self.send_get(path, query, &bearer_token).await?;
+        }
+        let result = decode_json(path, response).await;
+        drop(permit);
+        result
     }
 
     // Helper function to handle pagination
-    // Gets a single page of the the computer inventory
-    async fn get_computer_inventory_page(
+    // Gets a single page of one of the inventory endpoints
+    async fn get_inventory_page<T: DeserializeOwned>(
         &self,
-        section: &Vec<ComputerInventorySection>,
+        path: &str,
+        params: &[(String, String)],
         page: usize,
-    ) -> Result<JamfComputerInventoryResponse, JamfClientError> {
-        // Select for the sections being requested
-        let mut params = section
-            .iter()
-            .map(|s| ("section".to_string(), s.to_string()))
-            .collect::<Vec<(String, String)>>();
+    ) -> Result<JamfInventoryResponse<T>, JamfClientError> {
+        // Select for the sections, filter and sort being requested
+        let mut params = params.to_vec();
         params.push(("page".to_string(), page.to_string()));
-        params.push(("page-size".to_string(), PAGE_SIZE.to_string()));
+        params.push((
+            "page-size".to_string(),
+            self.config.pagination.page_size.to_string(),
+        ));
 
-        let response = get_client()
-            .await
-            .get(format!("{}/api/v1/computers-inventory", self.jamf_url))
-            .header("accept", "application/json")
-            .bearer_auth(self.bearer_token.clone())
-            .query(&params)
-            .send()
-            .await
-            .inspect_err(|e| error!("Failed to get computers inventory: {}", e))?;
-        Ok(response
-            .json::<JamfComputerInventoryResponse>()
+        self.get_json::<JamfInventoryResponse<T>>(path, &params)
             .await
-            .inspect_err(|e| error!("Failed to create ComputerInventoryResponse: {}", e))?)
+            .inspect_err(|e| error!("Failed to get inventory from {}: {}", path, e))
     }
-}
 
-impl JamfClientTrait for JamfClientImpl {
-    /// Fetch all computers from computer inventory - include data from sections provided
-    async fn get_computer_inventory(
+    /// Fetch every page of an inventory endpoint
+    async fn get_inventory<T: DeserializeOwned>(
         &self,
-        section: Vec<ComputerInventorySection>,
-    ) -> Result<JamfComputerInventoryResponse, JamfClientError> {
-        let mut inventory_response = self.get_computer_inventory_page(&section, 0).await?;
-        if inventory_response.total_count <= PAGE_SIZE {
+        path: &str,
+        params: Vec<(String, String)>,
+    ) -> Result<JamfInventoryResponse<T>, JamfClientError> {
+        let page_size = self.config.pagination.page_size;
+        let mut inventory_response = self.get_inventory_page::<T>(path, &params, 0).await?;
+        if inventory_response.total_count <= page_size {
             return Ok(inventory_response);
         }
 
-        let num_pages =
-            (inventory_response.total_count + PAGE_SIZE as usize - 1) / PAGE_SIZE as usize;
-        for page in 1..num_pages {
-            let pagination_response = self.get_computer_inventory_page(&section, page).await?;
-            inventory_response
-                .results
-                .extend(pagination_response.results);
+        // The first page tells us how many there are, the rest are fetched concurrently
+        let num_pages = inventory_response.total_count.div_ceil(page_size);
+        let pages = fetch_pages(
+            1..num_pages,
+            self.config.pagination.page_concurrency,
+            |page| self.get_inventory_page::<T>(path, &params, page),
+        )
+        .await?;
+        for page in pages {
+            inventory_response.results.extend(page.results);
         }
         Ok(inventory_response)
     }
 
+    /// Stream an inventory endpoint a page at a time, so the whole fleet is never held in memory
+    fn stream_inventory<T: DeserializeOwned + Send + 'static>(
+        &self,
+        path: &'static str,
+        params: Vec<(String, String)>,
+    ) -> BoxStream<'static, Result<Vec<T>, JamfClientError>> {
+        let client = self.clone();
+        let params = Arc::new(params);
+        stream::once(async move {
+            let first_page = client.get_inventory_page::<T>(path, &params, 0).await?;
+            let num_pages = first_page
+                .total_count
+                .div_ceil(client.config.pagination.page_size);
+            let concurrency = client.config.pagination.page_concurrency;
+            // Only `concurrency` pages are buffered at a time, later pages are fetched as the stream is consumed
+            let remaining_pages = stream::iter(1..num_pages)
+                .map(move |page| {
+                    let (client, params) = (client.clone(), params.clone());
+                    async move {
+                        client
+                            .get_inventory_page::<T>(path, &params, page)
+                            .await
+                            .map(|p| p.results)
+                    }
+                })
+                .buffered(concurrency.max(1));
+            Ok::<_, JamfClientError>(
+                stream::once(future::ready(Ok(first_page.results))).chain(remaining_pages),
+            )
+        })
+        .try_flatten()
+        .boxed()
+    }
+}
+
+impl JamfClientTrait for JamfClientImpl {
+    /// Fetch all computers matching the query from computer inventory - include data from sections provided
+    async fn get_computer_inventory(
+        &self,
+        query: ComputerInventoryQuery,
+    ) -> Result<JamfComputerInventoryResponse, JamfClientError> {
+        self.get_inventory(COMPUTERS_INVENTORY_PATH, query.params())
+            .await
+    }
+
+    fn stream_computer_inventory(
+        &self,
+        query: ComputerInventoryQuery,
+    ) -> BoxStream<'static, Result<Vec<JamfComputer>, JamfClientError>> {
+        self.stream_inventory(COMPUTERS_INVENTORY_PATH, query.params())
+    }
+
+    /// Fetch all mobile devices matching the query from mobile device inventory
+    async fn get_mobile_device_inventory(
+        &self,
+        query: MobileDeviceInventoryQuery,
+    ) -> Result<JamfMobileDeviceInventoryResponse, JamfClientError> {
+        self.get_inventory(MOBILE_DEVICES_INVENTORY_PATH, query.params())
+            .await
+    }
+
+    fn stream_mobile_device_inventory(
+        &self,
+        query: MobileDeviceInventoryQuery,
+    ) -> BoxStream<'static, Result<Vec<JamfMobileDevice>, JamfClientError>> {
+        self.stream_inventory(MOBILE_DEVICES_INVENTORY_PATH, query.params())
+    }
+
+    async fn get_mobile_device_detail(
+        &self,
+        id: &str,
+    ) -> Result<JamfMobileDeviceDetail, JamfClientError> {
+        self.get_json::<JamfMobileDeviceDetail>(
+            &format!("/api/v2/mobile-devices/{}/detail", id),
+            &[],
+        )
+        .await
+        .inspect_err(|e| error!("Failed to get mobile device {}: {}", id, e))
+    }
+
+    async fn get_computer_detail(&self, id: &str) -> Result<JamfComputer, JamfClientError> {
+        self.get_json::<JamfComputer>(&format!("/api/v1/computers-inventory-detail/{}", id), &[])
+            .await
+            .inspect_err(|e| error!("Failed to get computer {}: {}", id, e))
+    }
+
     /// Get all OS managed updates from Jamf server to determine if devices are up to date
     async fn get_os_managed_updates(&self) -> Result<JamfAvailableUpdates, JamfClientError> {
-        let response = get_client()
-            .await
-            .get(format!(
-                "{}/api/v1/managed-software-updates/available-updates",
-                self.jamf_url
-            ))
-            .header("accept", "application/json")
-            .bearer_auth(self.bearer_token.clone())
-            .send()
+        self.get_json::<JamfAvailableUpdates>(
+            "/api/v1/managed-software-updates/available-updates",
+            &[],
+        )
+        .await
+        .inspect_err(|e| error!("Failed to get available updates: {}", e))
+    }
+
+    async fn get_jamf_pro_version(&self) -> Result<JamfProVersion, JamfClientError> {
+        self.get_json::<JamfProVersion>("/api/v1/jamf-pro-version", &[])
             .await
-            .inspect_err(|e| error!("Failed to current Mac OS versions: {}", e))?;
+            .inspect_err(|e| error!("Failed to get Jamf Pro version: {}", e))
+    }
 
-        Ok(response
-            .json::<JamfAvailableUpdates>()
+    async fn get_auth_details(&self) -> Result<JamfAuthDetails, JamfClientError> {
+        self.get_json::<JamfAuthDetails>("/api/v1/auth", &[])
             .await
-            .inspect_err(|e| error!("Failed to get available updates: {}", e))?)
+            .inspect_err(|e| error!("Failed to get Jamf account details: {}", e))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::jamf::client::{ComputerInventorySection, JamfClient, JamfClientTrait};
+    use crate::jamf::{
+        auth::JamfCredentials,
+        client::{ComputerInventorySection, JamfClient, JamfClientTrait},
+        query::ComputerInventoryQuery,
+        registry::JamfClientRegistry,
+        transport::TransportConfig,
+    };
     use dotenv::dotenv;
-    use std::env;
+    use std::{
+        env,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::{check_status, decode_json, fetch_pages, JamfClientError};
 
-    use super::JamfClientImpl;
+    fn response(status: u16, body: &str) -> reqwest::Response {
+        http::Response::builder()
+            .status(status)
+            .header("retry-after", "12")
+            .body(body.to_string())
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn error_statuses_typed() {
+        let path = "/api/v1/computers-inventory";
+        assert!(check_status(path, response(200, "")).is_ok());
+        assert!(matches!(
+            check_status(path, response(401, "")),
+            Err(JamfClientError::AuthenticationFailed { .. })
+        ));
+        assert!(matches!(
+            check_status(path, response(403, "")),
+            Err(JamfClientError::PermissionDenied { .. })
+        ));
+        assert!(matches!(
+            check_status(path, response(404, "")),
+            Err(JamfClientError::NotFound { .. })
+        ));
+        assert!(matches!(
+            check_status(path, response(429, "")),
+            Err(JamfClientError::RateLimited { retry_after: Some(d), .. }) if d == Duration::from_secs(12)
+        ));
+        assert!(matches!(
+            check_status(path, response(503, "")),
+            Err(JamfClientError::ServerError { .. })
+        ));
+        assert!(matches!(
+            check_status(path, response(409, "")),
+            Err(JamfClientError::UnexpectedStatus { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn pages_fetched_concurrently_in_order() {
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let pages = fetch_pages(1..10, 3, |page| {
+            let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                // Later pages finish first, the results must still come back in page order
+                tokio::time::sleep(Duration::from_millis(20 - page as u64)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(page)
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(pages, (1..10).collect::<Vec<_>>());
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn failed_page_fails_fetch() {
+        let result = fetch_pages(1..5, 2, |page| async move {
+            match page {
+                3 => Err(JamfClientError::NotFound {
+                    path: "/api/v1/computers-inventory".to_string(),
+                }),
+                page => Ok(page),
+            }
+        })
+        .await;
+        assert!(matches!(result, Err(JamfClientError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn malformed_payload_keeps_snippet() {
+        let page = format!("<html><body>{}</body></html>", "Maintenance ".repeat(100));
+        let result = decode_json::<serde_json::Value>("/api/v1/auth", response(200, &page)).await;
+        match result {
+            Err(JamfClientError::MalformedPayload { snippet, .. }) => {
+                assert!(snippet.starts_with("<html><body>Maintenance"));
+                assert_eq!(snippet.chars().count(), 200);
+            }
+            other => panic!("Expected a malformed payload, got {:?}", other),
+        }
+    }
 
     async fn init() -> JamfClient {
         dotenv().ok();
         let username = env::var("USERNAME").expect("Please set username env var");
         let password = env::var("PASSWORD").expect("Please set password env var");
         let jamf_url = env::var("JAMF_URL").expect("Please set jamf_url env var");
-        JamfClient::Impl(
-            JamfClientImpl::new(username, password, jamf_url)
-                .await
-                .unwrap(),
-        )
+        let client = JamfClientRegistry::default()
+            .new_client(
+                "tenant",
+                JamfCredentials::Basic { username, password },
+                &jamf_url,
+                &TransportConfig::from_env().unwrap(),
+            )
+            .unwrap();
+        // Authenticate up front so invalid credentials are caught before the test runs
+        client.authenticate().await.unwrap();
+        JamfClient::Impl(client)
     }
 
     #[tokio::test]
@@ -193,10 +724,13 @@ mod tests {
         let provider = init().await;
 
         let inventory = provider
-            .get_computer_inventory(vec![
-                ComputerInventorySection::OperatingSystem,
-                ComputerInventorySection::General,
-            ])
+            .get_computer_inventory(ComputerInventoryQuery {
+                sections: vec![
+                    ComputerInventorySection::OperatingSystem,
+                    ComputerInventorySection::General,
+                ],
+                ..Default::default()
+            })
             .await
             .unwrap();
         assert_eq!(inventory.total_count, 4);
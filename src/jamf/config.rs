@@ -0,0 +1,91 @@
+use std::{env, str::FromStr};
+
+use super::{
+    circuit_breaker::CircuitBreakerConfig,
+    rate_limit::RateLimitConfig,
+    retry::RetryPolicy,
+    transport::{TransportConfig, TransportError},
+};
+
+// Largest page Jamf accepts for inventory endpoints
+pub(crate) static MAX_PAGE_SIZE: usize = 2000;
+
+pub(crate) fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// How paginated inventory endpoints are fetched
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaginationConfig {
+    /// Results per page, at most `MAX_PAGE_SIZE`
+    pub page_size: usize,
+    /// Pages fetched at the same time after the first one told us how many there are
+    pub page_concurrency: usize,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            page_size: 100,
+            page_concurrency: 4,
+        }
+    }
+}
+
+impl PaginationConfig {
+    pub fn new(page_size: usize, page_concurrency: usize) -> Self {
+        Self {
+            page_size: page_size.clamp(1, MAX_PAGE_SIZE),
+            page_concurrency: page_concurrency.max(1),
+        }
+    }
+
+    /// Read the config from `JAMF_PAGE_SIZE` and `JAMF_PAGE_CONCURRENCY`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self::new(
+            env_or("JAMF_PAGE_SIZE", default.page_size),
+            env_or("JAMF_PAGE_CONCURRENCY", default.page_concurrency),
+        )
+    }
+}
+
+/// Settings shared by every Jamf client
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JamfClientConfig {
+    pub retry_policy: RetryPolicy,
+    pub pagination: PaginationConfig,
+    /// Budget per Jamf instance, shared by every client talking to it
+    pub rate_limit: RateLimitConfig,
+    /// Defaults for connections that don't configure their own transport
+    pub transport: TransportConfig,
+    /// When to stop calling Jamf for a connection that keeps failing
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+impl JamfClientConfig {
+    pub fn from_env() -> Result<Self, TransportError> {
+        Ok(Self {
+            retry_policy: RetryPolicy::from_env(),
+            pagination: PaginationConfig::from_env(),
+            rate_limit: RateLimitConfig::from_env(),
+            transport: TransportConfig::from_env()?,
+            circuit_breaker: CircuitBreakerConfig::from_env(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PaginationConfig, MAX_PAGE_SIZE};
+
+    #[test]
+    fn page_size_capped_at_jamf_maximum() {
+        assert_eq!(PaginationConfig::new(5000, 4).page_size, MAX_PAGE_SIZE);
+        assert_eq!(PaginationConfig::new(0, 4).page_size, 1);
+        assert_eq!(PaginationConfig::new(500, 0).page_concurrency, 1);
+    }
+}
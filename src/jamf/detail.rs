@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    extension_attributes::{ExtensionAttributeValue, ExtensionAttributesConfig},
+    models::{
+        JamfComputer, JamfComputerApplication, JamfComputerHardware, JamfComputerSecurity,
+        JamfComputerUserAndLocation, JamfGatekeeperStatus, JamfMobileDeviceDetail,
+        JamfMobileDeviceLocation, JamfMobileDeviceSecurity, JamfSecureBootLevel, JamfSipStatus,
+    },
+    posture::{
+        check_in, compliance_verdict, computer_compliance, disk_encryption, security_checks,
+        CheckIn, ComplianceVerdict, DiskEncryption, SecurityChecks, StalenessConfig,
+    },
+    provider::{is_mobile_os_updated, is_os_updated, mobile_os_name},
+};
+
+/// Everything we know about a single computer, for the device detail page
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ComputerDetail {
+    device_id: Option<String>,
+    udid: Option<String>,
+    name: Option<String>,
+    serial_number: Option<String>,
+    asset_tag: Option<String>,
+    check_in: CheckIn,
+    hardware: Option<HardwareDetail>,
+    operating_system: Option<OperatingSystemDetail>,
+    security: Option<SecurityDetail>,
+    security_checks: SecurityChecks,
+    user: Option<UserDetail>,
+    /// Whether FileVault protects the boot volume, unknown if Jamf didn't report it
+    disk_encrypted: Option<bool>,
+    disk_encryption: Option<DiskEncryption>,
+    applications: Vec<ApplicationDetail>,
+    /// The extension attributes operators chose to expose, by their configured name
+    extension_attributes: BTreeMap<String, Option<ExtensionAttributeValue>>,
+    compliance: ComplianceVerdict,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct HardwareDetail {
+    make: String,
+    model: String,
+    model_identifier: Option<String>,
+    processor_type: Option<String>,
+    apple_silicon: Option<bool>,
+    total_ram_megabytes: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct OperatingSystemDetail {
+    name: String,
+    version: String,
+    build: String,
+    rapid_security_response: Option<String>,
+    /// Unknown for platforms Jamf doesn't list updates for, e.g. tvOS
+    is_latest: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct SecurityDetail {
+    sip_status: Option<JamfSipStatus>,
+    gatekeeper_status: Option<JamfGatekeeperStatus>,
+    firewall_enabled: Option<bool>,
+    xprotect_version: Option<String>,
+    secure_boot_level: Option<JamfSecureBootLevel>,
+    activation_lock_enabled: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct UserDetail {
+    username: Option<String>,
+    realname: Option<String>,
+    email: Option<String>,
+    position: Option<String>,
+    phone: Option<String>,
+    department_id: Option<String>,
+    building_id: Option<String>,
+    room: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ApplicationDetail {
+    name: Option<String>,
+    bundle_id: Option<String>,
+    version: Option<String>,
+    path: Option<String>,
+    mac_app_store: Option<bool>,
+    update_available: Option<bool>,
+}
+
+impl From<JamfComputerHardware> for HardwareDetail {
+    fn from(hardware: JamfComputerHardware) -> Self {
+        Self {
+            make: hardware.make,
+            model: hardware.model,
+            model_identifier: hardware.model_identifier,
+            processor_type: hardware.processor_type,
+            apple_silicon: hardware.apple_silicon,
+            total_ram_megabytes: hardware.total_ram_megabytes,
+        }
+    }
+}
+
+impl From<JamfComputerSecurity> for SecurityDetail {
+    fn from(security: JamfComputerSecurity) -> Self {
+        Self {
+            sip_status: security.sip_status,
+            gatekeeper_status: security.gatekeeper_status,
+            firewall_enabled: security.firewall_enabled,
+            xprotect_version: security.xprotect_version,
+            secure_boot_level: security.secure_boot_level,
+            activation_lock_enabled: security.activation_lock_enabled,
+        }
+    }
+}
+
+impl From<JamfComputerUserAndLocation> for UserDetail {
+    fn from(user: JamfComputerUserAndLocation) -> Self {
+        Self {
+            username: user.username,
+            realname: user.realname,
+            email: user.email,
+            position: user.position,
+            phone: user.phone,
+            department_id: user.department_id,
+            building_id: user.building_id,
+            room: user.room,
+        }
+    }
+}
+
+impl From<JamfComputerApplication> for ApplicationDetail {
+    fn from(application: JamfComputerApplication) -> Self {
+        Self {
+            name: application.name,
+            bundle_id: application.bundle_id,
+            version: application.version,
+            path: application.path,
+            mac_app_store: application.mac_app_store,
+            update_available: application.update_available,
+        }
+    }
+}
+
+/// Build the detail document from a computer fetched with every section
+pub(crate) fn convert_jamf_computer_detail(
+    jamf_computer: JamfComputer,
+    mac_os_versions: Vec<String>,
+    extension_attributes: &ExtensionAttributesConfig,
+    staleness: StalenessConfig,
+) -> ComputerDetail {
+    let extension_attributes = extension_attributes.values(&jamf_computer);
+    let general = jamf_computer.general;
+    let file_vault2_status = jamf_computer
+        .operating_system
+        .as_ref()
+        .and_then(|o| o.file_vault2_status);
+    let operating_system = jamf_computer
+        .operating_system
+        .map(|os| OperatingSystemDetail {
+            is_latest: Some(is_os_updated(os.version.clone(), mac_os_versions)),
+            name: os.name,
+            version: os.version,
+            build: os.build,
+            rapid_security_response: os.rapid_security_response,
+        });
+    let disk_encryption = disk_encryption(
+        jamf_computer.disk_encryption,
+        jamf_computer.storage,
+        file_vault2_status,
+    );
+    let disk_encrypted = disk_encryption
+        .as_ref()
+        .and_then(DiskEncryption::is_encrypted);
+    let security_checks = security_checks(jamf_computer.security.as_ref());
+    let check_in = check_in(
+        general
+            .as_ref()
+            .and_then(|g| g.last_contact_time.as_deref()),
+        general.as_ref().and_then(|g| g.report_date.as_deref()),
+        staleness,
+        Utc::now(),
+    );
+    let compliance = computer_compliance(
+        operating_system.as_ref().and_then(|os| os.is_latest),
+        disk_encrypted,
+        &security_checks,
+        &check_in,
+    );
+
+    ComputerDetail {
+        device_id: jamf_computer.id,
+        udid: jamf_computer.udid,
+        serial_number: jamf_computer
+            .hardware
+            .as_ref()
+            .and_then(|h| h.serial_number.clone()),
+        name: general.as_ref().map(|g| g.name.clone()),
+        asset_tag: general.as_ref().and_then(|g| g.asset_tag.clone()),
+        check_in,
+        hardware: jamf_computer.hardware.map(HardwareDetail::from),
+        operating_system,
+        security: jamf_computer.security.map(SecurityDetail::from),
+        security_checks,
+        user: jamf_computer.user_and_location.map(UserDetail::from),
+        disk_encrypted,
+        disk_encryption,
+        applications: jamf_computer
+            .applications
+            .unwrap_or_default()
+            .into_iter()
+            .map(ApplicationDetail::from)
+            .collect(),
+        extension_attributes,
+        compliance,
+    }
+}
+
+/// Everything we know about a single iPhone, iPad or Apple TV
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct MobileDeviceDetail {
+    device_id: Option<String>,
+    udid: Option<String>,
+    name: Option<String>,
+    serial_number: Option<String>,
+    asset_tag: Option<String>,
+    /// Mobile devices check in by sending inventory, so only `report_date` is set
+    check_in: CheckIn,
+    model: Option<String>,
+    model_identifier: Option<String>,
+    managed: Option<bool>,
+    supervised: Option<bool>,
+    operating_system: Option<OperatingSystemDetail>,
+    security: Option<MobileSecurityDetail>,
+    user: Option<UserDetail>,
+    compliance: ComplianceVerdict,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct MobileSecurityDetail {
+    passcode_present: Option<bool>,
+    passcode_compliant: Option<bool>,
+    data_protected: Option<bool>,
+    activation_lock_enabled: Option<bool>,
+    jailbreak_detected: Option<bool>,
+}
+
+impl From<JamfMobileDeviceSecurity> for MobileSecurityDetail {
+    fn from(security: JamfMobileDeviceSecurity) -> Self {
+        Self {
+            passcode_present: security.passcode_present,
+            passcode_compliant: security.passcode_compliant,
+            data_protected: security.data_protected,
+            activation_lock_enabled: security.activation_lock_enabled,
+            jailbreak_detected: security.jail_break_detected,
+        }
+    }
+}
+
+/// The user a device is assigned to, `None` if Jamf has nobody on record
+pub(crate) fn assigned_user(user: impl Into<UserDetail>) -> Option<UserDetail> {
+    let user = user.into();
+    (user.username.is_some() || user.email.is_some() || user.realname.is_some()).then_some(user)
+}
+
+impl From<JamfMobileDeviceLocation> for UserDetail {
+    fn from(location: JamfMobileDeviceLocation) -> Self {
+        Self {
+            username: location.username,
+            realname: location.real_name,
+            email: location.email_address,
+            position: location.position,
+            phone: location.phone_number,
+            department_id: location.department_id,
+            building_id: location.building_id,
+            room: location.room,
+        }
+    }
+}
+
+/// Build the detail document for a mobile device, its OS is judged against the iOS update list
+pub(crate) fn convert_jamf_mobile_device_detail(
+    jamf_mobile_device: JamfMobileDeviceDetail,
+    ios_versions: Vec<String>,
+    staleness: StalenessConfig,
+) -> MobileDeviceDetail {
+    let ios = jamf_mobile_device.ios;
+    let model_identifier = ios.as_ref().and_then(|i| i.model_identifier.clone());
+    let os_name = mobile_os_name(
+        jamf_mobile_device.device_type.as_deref(),
+        model_identifier.as_deref(),
+    );
+    let operating_system = jamf_mobile_device
+        .os_version
+        .map(|version| OperatingSystemDetail {
+            is_latest: is_mobile_os_updated(
+                os_name.as_deref(),
+                Some(version.clone()),
+                ios_versions,
+            ),
+            name: os_name.clone().unwrap_or_default(),
+            version,
+            build: jamf_mobile_device.os_build.unwrap_or_default(),
+            rapid_security_response: jamf_mobile_device.os_rapid_security_response,
+        });
+    let check_in = check_in(
+        None,
+        jamf_mobile_device
+            .last_inventory_update_timestamp
+            .as_deref(),
+        staleness,
+        Utc::now(),
+    );
+    let compliance = compliance_verdict(&[
+        (
+            "os_outdated",
+            operating_system.as_ref().and_then(|os| os.is_latest),
+        ),
+        check_in.compliance_check(),
+    ]);
+
+    MobileDeviceDetail {
+        device_id: jamf_mobile_device.id,
+        udid: jamf_mobile_device.udid,
+        name: jamf_mobile_device.name,
+        serial_number: jamf_mobile_device.serial_number,
+        asset_tag: jamf_mobile_device.asset_tag,
+        check_in,
+        model: ios.as_ref().and_then(|i| i.model.clone()),
+        model_identifier,
+        managed: jamf_mobile_device.managed,
+        supervised: ios.as_ref().and_then(|i| i.supervised),
+        operating_system,
+        security: ios.and_then(|i| i.security).map(MobileSecurityDetail::from),
+        user: jamf_mobile_device.location.map(UserDetail::from),
+        compliance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jamf::{
+        extension_attributes::ExtensionAttributesConfig,
+        models::{JamfComputer, JamfComputerInventoryResponse},
+        posture::StalenessConfig,
+    };
+
+    use chrono::{Duration, Utc};
+
+    use super::{convert_jamf_computer_detail, ComplianceVerdict};
+
+    fn sample_computers() -> JamfComputerInventoryResponse {
+        serde_json::from_str(include_str!("testdata/computers_inventory.json")).unwrap()
+    }
+
+    // The sample data is from 2024, it shouldn't turn stale as time goes by
+    fn not_stale() -> StalenessConfig {
+        StalenessConfig {
+            stale_after_days: 365_000,
+        }
+    }
+
+    #[test]
+    fn detail_built_from_every_section() {
+        let computer = sample_computers().results.remove(0);
+        let detail = convert_jamf_computer_detail(
+            computer,
+            vec!["14.5".to_string()],
+            &ExtensionAttributesConfig::default(),
+            not_stale(),
+        );
+
+        assert_eq!(detail.device_id.as_deref(), Some("12"));
+        assert_eq!(detail.serial_number.as_deref(), Some("FVFDK0XJQ6L4"));
+        assert_eq!(
+            detail.hardware.unwrap().model_identifier.as_deref(),
+            Some("MacBookAir10,1")
+        );
+        assert_eq!(detail.operating_system.unwrap().is_latest, Some(false));
+        assert_eq!(detail.security.unwrap().firewall_enabled, Some(true));
+        assert_eq!(
+            detail.user.unwrap().email.as_deref(),
+            Some("peter@example.com")
+        );
+        assert_eq!(detail.disk_encrypted, Some(true));
+        assert!(detail.disk_encryption.is_some());
+        assert_eq!(detail.applications.len(), 2);
+        assert_eq!(
+            detail.compliance,
+            ComplianceVerdict {
+                compliant: Some(false),
+                failures: vec!["os_outdated".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn compliance_unknown_without_os() {
+        let mut computers = sample_computers().results;
+        let detail = convert_jamf_computer_detail(
+            JamfComputer::default(),
+            vec![],
+            &ExtensionAttributesConfig::default(),
+            not_stale(),
+        );
+        assert_eq!(detail.compliance.compliant, None);
+        assert!(detail.applications.is_empty());
+
+        let unencrypted = computers.remove(1);
+        let mut computer = computers.remove(0);
+        computer.operating_system.as_mut().unwrap().version = "14.5".to_string();
+        let detail = convert_jamf_computer_detail(
+            computer,
+            vec!["14.5".to_string()],
+            &ExtensionAttributesConfig::default(),
+            not_stale(),
+        );
+        assert_eq!(detail.compliance.compliant, Some(true));
+        assert!(detail.compliance.failures.is_empty());
+
+        let detail = convert_jamf_computer_detail(
+            unencrypted,
+            vec!["13.6.1".to_string()],
+            &ExtensionAttributesConfig::default(),
+            not_stale(),
+        );
+        assert_eq!(detail.disk_encrypted, Some(false));
+        assert_eq!(
+            detail.compliance.failures,
+            ["disk_not_encrypted", "firewall_disabled"]
+        );
+    }
+
+    #[test]
+    fn stale_computer_not_compliant() {
+        let mut computer = sample_computers().results.remove(0);
+        computer.operating_system.as_mut().unwrap().version = "14.5".to_string();
+        let checked_in = (Utc::now() - Duration::days(90)).to_rfc3339();
+        let general = computer.general.as_mut().unwrap();
+        general.last_contact_time = Some(checked_in.clone());
+        general.report_date = Some(checked_in);
+        let detail = convert_jamf_computer_detail(
+            computer,
+            vec!["14.5".to_string()],
+            &ExtensionAttributesConfig::default(),
+            StalenessConfig::default(),
+        );
+        assert!(detail.check_in.is_stale());
+        assert_eq!(
+            detail.compliance,
+            ComplianceVerdict {
+                compliant: Some(false),
+                failures: vec!["stale_check_in".to_string()],
+            }
+        );
+    }
+}
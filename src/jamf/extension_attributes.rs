@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use super::models::{JamfComputer, JamfExtensionAttribute, JamfExtensionAttributeDataType};
+
+/// An extension attribute we expose, under a name that doesn't change when the Jamf admin renames it
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ExposedAttribute {
+    name: String,
+    jamf_name: String,
+}
+
+/// Which of the Jamf admin's extension attributes show up on devices
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtensionAttributesConfig {
+    attributes: Vec<ExposedAttribute>,
+}
+
+impl ExtensionAttributesConfig {
+    /// Parse `name=Jamf attribute name` pairs separated by commas, e.g.
+    /// `edr_version=CrowdStrike Sensor Version,cost_center=Cost Center`
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut attributes: Vec<ExposedAttribute> = vec![];
+        for pair in config.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, jamf_name) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=Jamf attribute name, got {}", pair))?;
+            let (name, jamf_name) = (name.trim(), jamf_name.trim());
+            // Used as a JSON key and in filters, so keep it to something callers can type
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            {
+                return Err(format!(
+                    "Invalid name {}, use lowercase letters, digits and underscores",
+                    name
+                ));
+            }
+            if jamf_name.is_empty() {
+                return Err(format!("Missing Jamf attribute name for {}", name));
+            }
+            if attributes.iter().any(|a| a.name == name) {
+                return Err(format!("{} is configured more than once", name));
+            }
+            attributes.push(ExposedAttribute {
+                name: name.to_string(),
+                jamf_name: jamf_name.to_string(),
+            });
+        }
+        Ok(Self { attributes })
+    }
+
+    /// Read the config from `JAMF_EXTENSION_ATTRIBUTES`, no extension attributes are exposed if unset
+    pub fn from_env() -> Self {
+        std::env::var("JAMF_EXTENSION_ATTRIBUTES")
+            .map(|config| {
+                Self::parse(&config)
+                    .unwrap_or_else(|e| panic!("Invalid JAMF_EXTENSION_ATTRIBUTES: {}", e))
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+
+    /// The exposed attributes of a computer, `None` for the ones it doesn't have a value for
+    pub(crate) fn values(
+        &self,
+        computer: &JamfComputer,
+    ) -> BTreeMap<String, Option<ExtensionAttributeValue>> {
+        let reported = jamf_extension_attributes(computer).collect::<Vec<_>>();
+        self.attributes
+            .iter()
+            .map(|exposed| {
+                let value = reported
+                    .iter()
+                    .find(|a| a.name.as_deref() == Some(exposed.jamf_name.as_str()))
+                    .and_then(|a| ExtensionAttributeValue::from_jamf(a));
+                (exposed.name.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Parse `name:value` filters, e.g. `cost_center:R&D`, the value is everything after the first
+    /// colon so it can contain commas and colons
+    pub(crate) fn parse_filters(
+        &self,
+        filters: &[impl AsRef<str>],
+    ) -> Result<Vec<ExtensionAttributeFilter>, String> {
+        filters
+            .iter()
+            .map(|f| f.as_ref().trim())
+            .filter(|f| !f.is_empty())
+            .map(|filter| {
+                let (name, value) = filter
+                    .split_once(':')
+                    .ok_or_else(|| format!("Expected name:value, got {}", filter))?;
+                if !self.attributes.iter().any(|a| a.name == name) {
+                    return Err(format!("Unknown extension attribute {}", name));
+                }
+                Ok(ExtensionAttributeFilter {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Jamf returns attributes at the top level and in the section they were assigned to
+fn jamf_extension_attributes(
+    computer: &JamfComputer,
+) -> impl Iterator<Item = &JamfExtensionAttribute> {
+    computer
+        .extension_attributes
+        .iter()
+        .flatten()
+        .chain(
+            computer
+                .general
+                .iter()
+                .flat_map(|g| &g.extension_attributes),
+        )
+        .chain(
+            computer
+                .hardware
+                .iter()
+                .flat_map(|h| &h.extension_attributes),
+        )
+        .chain(
+            computer
+                .operating_system
+                .iter()
+                .flat_map(|o| &o.extension_attributes),
+        )
+        .chain(
+            computer
+                .user_and_location
+                .iter()
+                .flat_map(|u| &u.extension_attributes),
+        )
+        .chain(
+            computer
+                .purchasing
+                .iter()
+                .flat_map(|p| &p.extension_attributes),
+        )
+}
+
+/// Jamf sends every value as a string, this is the value in the attribute's data type
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ExtensionAttributeValue {
+    Integer(i64),
+    /// Jamf dates have no time zone
+    Date(NaiveDateTime),
+    String(String),
+    Multiple(Vec<ExtensionAttributeValue>),
+}
+
+// Jamf stores dates as `2024-05-01 13:45:00`, a date without a time is midnight
+fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+impl ExtensionAttributeValue {
+    /// `None` for an empty value or one that doesn't fit the data type, e.g. a script that printed
+    /// `Not installed` for an integer attribute
+    fn parse(data_type: Option<JamfExtensionAttributeDataType>, value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        match data_type {
+            Some(JamfExtensionAttributeDataType::Integer) => value.parse().ok().map(Self::Integer),
+            Some(JamfExtensionAttributeDataType::Date) => parse_date(value).map(Self::Date),
+            _ => Some(Self::String(value.to_string())),
+        }
+    }
+
+    fn from_jamf(attribute: &JamfExtensionAttribute) -> Option<Self> {
+        if attribute.multi_value.unwrap_or(false) {
+            let values = attribute
+                .values
+                .iter()
+                .filter_map(|v| Self::parse(attribute.data_type, v))
+                .collect::<Vec<_>>();
+            return (!values.is_empty()).then_some(Self::Multiple(values));
+        }
+        attribute
+            .values
+            .first()
+            .and_then(|v| Self::parse(attribute.data_type, v))
+    }
+
+    /// Compared in the value's type, strings ignore case and a multi value attribute matches if
+    /// any of its values does
+    fn matches(&self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            Self::Integer(i) => value.parse() == Ok(*i),
+            // A date without a time matches the whole day
+            Self::Date(d) => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                Ok(date) => d.date() == date,
+                Err(_) => parse_date(value) == Some(*d),
+            },
+            Self::String(s) => s.eq_ignore_ascii_case(value),
+            Self::Multiple(values) => values.iter().any(|v| v.matches(value)),
+        }
+    }
+}
+
+/// Only devices whose exposed attribute `name` has `value`
+/// Jamf can't filter on extension attributes, so these are applied to the devices it returned
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ExtensionAttributeFilter {
+    name: String,
+    value: String,
+}
+
+impl ExtensionAttributeFilter {
+    pub(crate) fn matches(
+        &self,
+        values: &BTreeMap<String, Option<ExtensionAttributeValue>>,
+    ) -> bool {
+        values
+            .get(&self.name)
+            .and_then(Option::as_ref)
+            .is_some_and(|v| v.matches(&self.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::jamf::models::{JamfComputerInventoryResponse, JamfExtensionAttribute};
+
+    use super::{ExtensionAttributeValue, ExtensionAttributesConfig};
+
+    fn attribute(data_type: &str, multi_value: bool, values: &[&str]) -> JamfExtensionAttribute {
+        serde_json::from_value(serde_json::json!({
+            "name": "Test",
+            "dataType": data_type,
+            "multiValue": multi_value,
+            "values": values,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn values_parsed_in_their_data_type() {
+        assert_eq!(
+            ExtensionAttributeValue::from_jamf(&attribute("INTEGER", false, &["42"])),
+            Some(ExtensionAttributeValue::Integer(42))
+        );
+        assert_eq!(
+            ExtensionAttributeValue::from_jamf(&attribute("INTEGER", false, &["Not installed"])),
+            None
+        );
+        assert_eq!(
+            ExtensionAttributeValue::from_jamf(&attribute("DATE", false, &["2024-05-01 13:45:00"])),
+            Some(ExtensionAttributeValue::Date(
+                NaiveDate::from_ymd_opt(2024, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(13, 45, 0)
+                    .unwrap()
+            ))
+        );
+        let date =
+            ExtensionAttributeValue::from_jamf(&attribute("DATE", false, &["2024-05-01"])).unwrap();
+        assert!(date.matches("2024-05-01"));
+        assert!(!date.matches("2024-05-01 13:45:00"));
+        assert_eq!(
+            ExtensionAttributeValue::from_jamf(&attribute("STRING", false, &[""])),
+            None
+        );
+        let multiple =
+            ExtensionAttributeValue::from_jamf(&attribute("STRING", true, &["VPN", "", "EDR"]))
+                .unwrap();
+        assert_eq!(
+            serde_json::to_value(&multiple).unwrap(),
+            serde_json::json!(["VPN", "EDR"])
+        );
+        assert!(multiple.matches("edr"));
+        assert!(!multiple.matches("MDM"));
+    }
+
+    #[test]
+    fn exposed_attributes_from_inventory() {
+        let config = ExtensionAttributesConfig::parse(
+            "edr_version=CrowdStrike Sensor Version, days_since_reboot=Days Since Last Reboot,cost_center=Cost Center",
+        )
+        .unwrap();
+        let inventory: JamfComputerInventoryResponse =
+            serde_json::from_str(include_str!("testdata/computers_inventory.json")).unwrap();
+        let computer = inventory
+            .results
+            .iter()
+            .find(|c| c.extension_attributes.is_some())
+            .unwrap();
+        let values = config.values(computer);
+        assert_eq!(
+            serde_json::to_value(&values).unwrap(),
+            serde_json::json!({
+                "cost_center": null,
+                "days_since_reboot": 3,
+                "edr_version": "7.14.17706.0",
+            })
+        );
+
+        let filters = config.parse_filters(&["days_since_reboot:3"]).unwrap();
+        assert!(filters[0].matches(&values));
+        let filters = config
+            .parse_filters(&["cost_center:R&D, Sales", "edr_version:7.14.17706.0"])
+            .unwrap();
+        assert_eq!(filters[0].value, "R&D, Sales");
+        assert!(!filters[0].matches(&values));
+        assert!(filters[1].matches(&values));
+        assert!(config.parse_filters(&["serial:1"]).is_err());
+    }
+
+    #[test]
+    fn invalid_config_rejected() {
+        assert!(ExtensionAttributesConfig::parse("").unwrap().is_empty());
+        assert!(ExtensionAttributesConfig::parse("Cost Center").is_err());
+        assert!(ExtensionAttributesConfig::parse("Cost Center=Cost Center").is_err());
+        assert!(ExtensionAttributesConfig::parse("cost_center=").is_err());
+        assert!(ExtensionAttributesConfig::parse("a=A,a=B").is_err());
+    }
+}
@@ -1,3 +1,17 @@
+pub mod applications;
+pub mod auth;
+pub mod cache;
+pub mod circuit_breaker;
 pub mod client;
+pub mod config;
+pub mod detail;
+pub mod extension_attributes;
 pub mod models;
+pub mod posture;
 pub mod provider;
+pub mod query;
+pub mod rate_limit;
+pub mod registry;
+pub mod retry;
+pub mod status;
+pub mod transport;
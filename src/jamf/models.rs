@@ -7,6 +7,43 @@ pub(crate) struct JamfAuthReponse {
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfOAuthResponse {
+    pub(crate) access_token: String,
+    pub(crate) token_type: String,
+    pub(crate) scope: Option<String>,
+    /// Lifetime of the token in seconds
+    pub(crate) expires_in: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfProVersion {
+    pub(crate) version: String,
+}
+
+/// Response of `/api/v1/auth`, details of the account the token was issued to
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfAuthDetails {
+    pub(crate) account: Option<JamfAccount>,
+    /// Privileges can also be granted through the groups the account is a member of
+    #[serde(rename = "accountGroups", default)]
+    pub(crate) account_groups: Vec<JamfAccountGroup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfAccount {
+    pub(crate) username: Option<String>,
+    #[serde(default)]
+    pub(crate) privileges: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfAccountGroup {
+    #[serde(default)]
+    pub(crate) privileges: Vec<String>,
+}
+
+/// A computer from computer inventory, only the sections that were requested are set
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct JamfComputer {
     pub(crate) hardware: Option<JamfComputerHardware>,
     pub(crate) software: Option<JamfComputerSoftware>,
@@ -15,15 +52,58 @@ pub(crate) struct JamfComputer {
     pub(crate) general: Option<JamfComputerGeneral>,
     pub(crate) id: Option<String>,
     pub(crate) udid: Option<String>,
+    #[serde(rename = "diskEncryption")]
+    pub(crate) disk_encryption: Option<JamfComputerDiskEncryption>,
+    pub(crate) purchasing: Option<JamfComputerPurchasing>,
+    pub(crate) applications: Option<Vec<JamfComputerApplication>>,
+    pub(crate) storage: Option<JamfComputerStorage>,
+    #[serde(rename = "userAndLocation")]
+    pub(crate) user_and_location: Option<JamfComputerUserAndLocation>,
+    #[serde(rename = "configurationProfiles")]
+    pub(crate) configuration_profiles: Option<Vec<JamfComputerConfigurationProfile>>,
+    pub(crate) printers: Option<Vec<JamfComputerPrinter>>,
+    pub(crate) services: Option<Vec<JamfComputerService>>,
+    #[serde(rename = "localUserAccounts")]
+    pub(crate) local_user_accounts: Option<Vec<JamfComputerLocalUserAccount>>,
+    pub(crate) certificates: Option<Vec<JamfComputerCertificate>>,
+    pub(crate) attachments: Option<Vec<JamfComputerAttachment>>,
+    pub(crate) plugins: Option<Vec<JamfComputerPlugin>>,
+    #[serde(rename = "packageReceipts")]
+    pub(crate) package_receipts: Option<JamfComputerPackageReceipts>,
+    pub(crate) fonts: Option<Vec<JamfComputerFont>>,
+    pub(crate) security: Option<JamfComputerSecurity>,
+    #[serde(rename = "licensedSoftware")]
+    pub(crate) licensed_software: Option<Vec<JamfComputerLicensedSoftware>>,
+    pub(crate) ibeacons: Option<Vec<JamfComputerIbeacon>>,
+    #[serde(rename = "softwareUpdates")]
+    pub(crate) software_updates: Option<Vec<JamfSoftwareUpdate>>,
+    #[serde(rename = "extensionAttributes")]
+    pub(crate) extension_attributes: Option<Vec<JamfExtensionAttribute>>,
+    #[serde(rename = "contentCaching")]
+    pub(crate) content_caching: Option<JamfComputerContentCaching>,
+    #[serde(rename = "groupMemberships")]
+    pub(crate) group_memberships: Option<Vec<JamfComputerGroupMembership>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct JamfComputerOperatingSystem {
     pub(crate) name: String,
     pub(crate) version: String,
     pub(crate) build: String,
     #[serde(rename = "softwareUpdates")]
     pub(crate) software_updates: Option<Vec<JamfSoftwareUpdate>>,
+    #[serde(rename = "supplementalBuildVersion")]
+    pub(crate) supplemental_build_version: Option<String>,
+    #[serde(rename = "rapidSecurityResponse")]
+    pub(crate) rapid_security_response: Option<String>,
+    #[serde(rename = "activeDirectoryStatus")]
+    pub(crate) active_directory_status: Option<String>,
+    #[serde(rename = "fileVault2Status")]
+    pub(crate) file_vault2_status: Option<JamfFileVault2Status>,
+    #[serde(rename = "softwareUpdateDeviceId")]
+    pub(crate) software_update_device_id: Option<String>,
+    #[serde(rename = "extensionAttributes", default)]
+    pub(crate) extension_attributes: Vec<JamfExtensionAttribute>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -34,15 +114,137 @@ pub(crate) struct JamfSoftwareUpdate {
     pub(crate) package_name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct JamfComputerGeneral {
     pub(crate) name: String,
+    #[serde(rename = "lastIpAddress")]
+    pub(crate) last_ip_address: Option<String>,
+    #[serde(rename = "lastReportedIp")]
+    pub(crate) last_reported_ip: Option<String>,
+    #[serde(rename = "jamfBinaryVersion")]
+    pub(crate) jamf_binary_version: Option<String>,
+    pub(crate) platform: Option<String>,
+    pub(crate) barcode1: Option<String>,
+    pub(crate) barcode2: Option<String>,
+    #[serde(rename = "assetTag")]
+    pub(crate) asset_tag: Option<String>,
+    #[serde(rename = "remoteManagement")]
+    pub(crate) remote_management: Option<JamfRemoteManagement>,
+    pub(crate) supervised: Option<bool>,
+    #[serde(rename = "mdmCapable")]
+    pub(crate) mdm_capable: Option<JamfMdmCapability>,
+    #[serde(rename = "reportDate")]
+    pub(crate) report_date: Option<String>,
+    #[serde(rename = "lastContactTime")]
+    pub(crate) last_contact_time: Option<String>,
+    #[serde(rename = "lastCloudBackupDate")]
+    pub(crate) last_cloud_backup_date: Option<String>,
+    #[serde(rename = "lastEnrolledDate")]
+    pub(crate) last_enrolled_date: Option<String>,
+    #[serde(rename = "mdmProfileExpiration")]
+    pub(crate) mdm_profile_expiration: Option<String>,
+    #[serde(rename = "initialEntryDate")]
+    pub(crate) initial_entry_date: Option<String>,
+    #[serde(rename = "distributionPoint")]
+    pub(crate) distribution_point: Option<String>,
+    #[serde(rename = "enrollmentMethod")]
+    pub(crate) enrollment_method: Option<JamfEnrollmentMethod>,
+    pub(crate) site: Option<JamfSite>,
+    #[serde(rename = "itunesStoreAccountActive")]
+    pub(crate) itunes_store_account_active: Option<bool>,
+    #[serde(rename = "enrolledViaAutomatedDeviceEnrollment")]
+    pub(crate) enrolled_via_automated_device_enrollment: Option<bool>,
+    #[serde(rename = "userApprovedMdm")]
+    pub(crate) user_approved_mdm: Option<bool>,
+    #[serde(rename = "declarativeDeviceManagementEnabled")]
+    pub(crate) declarative_device_management_enabled: Option<bool>,
+    #[serde(rename = "managementId")]
+    pub(crate) management_id: Option<String>,
+    #[serde(rename = "extensionAttributes", default)]
+    pub(crate) extension_attributes: Vec<JamfExtensionAttribute>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfRemoteManagement {
+    pub(crate) managed: Option<bool>,
+    #[serde(rename = "managementUsername")]
+    pub(crate) management_username: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfMdmCapability {
+    pub(crate) capable: Option<bool>,
+    #[serde(rename = "capableUsers", default)]
+    pub(crate) capable_users: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfEnrollmentMethod {
+    pub(crate) id: Option<String>,
+    #[serde(rename = "objectName")]
+    pub(crate) object_name: Option<String>,
+    #[serde(rename = "objectType")]
+    pub(crate) object_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfSite {
+    pub(crate) id: Option<String>,
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct JamfComputerHardware {
     pub(crate) make: String,
     pub(crate) model: String,
+    #[serde(rename = "modelIdentifier")]
+    pub(crate) model_identifier: Option<String>,
+    #[serde(rename = "serialNumber")]
+    pub(crate) serial_number: Option<String>,
+    #[serde(rename = "processorSpeedMhz")]
+    pub(crate) processor_speed_mhz: Option<i64>,
+    #[serde(rename = "processorCount")]
+    pub(crate) processor_count: Option<i64>,
+    #[serde(rename = "coreCount")]
+    pub(crate) core_count: Option<i64>,
+    #[serde(rename = "processorType")]
+    pub(crate) processor_type: Option<String>,
+    #[serde(rename = "processorArchitecture")]
+    pub(crate) processor_architecture: Option<String>,
+    #[serde(rename = "busSpeedMhz")]
+    pub(crate) bus_speed_mhz: Option<i64>,
+    #[serde(rename = "cacheSizeKilobytes")]
+    pub(crate) cache_size_kilobytes: Option<i64>,
+    #[serde(rename = "networkAdapterType")]
+    pub(crate) network_adapter_type: Option<String>,
+    #[serde(rename = "macAddress")]
+    pub(crate) mac_address: Option<String>,
+    #[serde(rename = "altNetworkAdapterType")]
+    pub(crate) alt_network_adapter_type: Option<String>,
+    #[serde(rename = "altMacAddress")]
+    pub(crate) alt_mac_address: Option<String>,
+    #[serde(rename = "totalRamMegabytes")]
+    pub(crate) total_ram_megabytes: Option<i64>,
+    #[serde(rename = "openRamSlots")]
+    pub(crate) open_ram_slots: Option<i64>,
+    #[serde(rename = "batteryCapacityPercent")]
+    pub(crate) battery_capacity_percent: Option<i64>,
+    #[serde(rename = "smcVersion")]
+    pub(crate) smc_version: Option<String>,
+    #[serde(rename = "nicSpeed")]
+    pub(crate) nic_speed: Option<String>,
+    #[serde(rename = "opticalDrive")]
+    pub(crate) optical_drive: Option<String>,
+    #[serde(rename = "bootRom")]
+    pub(crate) boot_rom: Option<String>,
+    #[serde(rename = "bleCapable")]
+    pub(crate) ble_capable: Option<bool>,
+    #[serde(rename = "supportsIosAppInstalls")]
+    pub(crate) supports_ios_app_installs: Option<bool>,
+    #[serde(rename = "appleSilicon")]
+    pub(crate) apple_silicon: Option<bool>,
+    #[serde(rename = "extensionAttributes", default)]
+    pub(crate) extension_attributes: Vec<JamfExtensionAttribute>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -51,11 +253,619 @@ pub(crate) struct JamfComputerSoftware {
     pub(crate) available_updates: Vec<String>,
 }
 
+/// FileVault state of a disk or the whole computer, as Jamf reports it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfFileVault2Status {
+    NotApplicable,
+    NotEncrypted,
+    BootEncrypted,
+    SomeEncrypted,
+    AllEncrypted,
+    // Anything a newer Jamf version adds
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfPartitionFileVault2State {
+    Unknown,
+    Unencrypted,
+    Ineligible,
+    Decrypted,
+    Decrypting,
+    Encrypted,
+    Encrypting,
+    Restart,
+    OptimizingEncryption,
+    OptimizingDecryption,
+    Failed,
+    #[serde(other)]
+    Other,
+}
+
+/// Whether the personal recovery key Jamf escrowed still unlocks the disk
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfRecoveryKeyValidity {
+    Valid,
+    Invalid,
+    Unknown,
+    NotApplicable,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfComputerDiskEncryption {
+    #[serde(rename = "bootPartitionEncryptionDetails")]
+    pub(crate) boot_partition_encryption_details: Option<JamfPartitionEncryption>,
+    #[serde(rename = "individualRecoveryKeyValidityStatus")]
+    pub(crate) individual_recovery_key_validity_status: Option<JamfRecoveryKeyValidity>,
+    #[serde(rename = "institutionalRecoveryKeyPresent")]
+    pub(crate) institutional_recovery_key_present: Option<bool>,
+    #[serde(rename = "diskEncryptionConfigurationName")]
+    pub(crate) disk_encryption_configuration_name: Option<String>,
+    #[serde(rename = "fileVault2EnabledUserNames", default)]
+    pub(crate) file_vault2_enabled_user_names: Vec<String>,
+    #[serde(rename = "fileVault2EligibilityMessage")]
+    pub(crate) file_vault2_eligibility_message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfPartitionEncryption {
+    #[serde(rename = "partitionName")]
+    pub(crate) partition_name: Option<String>,
+    #[serde(rename = "partitionFileVault2State")]
+    pub(crate) partition_file_vault2_state: Option<JamfPartitionFileVault2State>,
+    #[serde(rename = "partitionFileVault2Percent")]
+    pub(crate) partition_file_vault2_percent: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerPurchasing {
+    pub(crate) leased: Option<bool>,
+    pub(crate) purchased: Option<bool>,
+    #[serde(rename = "poNumber")]
+    pub(crate) po_number: Option<String>,
+    #[serde(rename = "poDate")]
+    pub(crate) po_date: Option<String>,
+    pub(crate) vendor: Option<String>,
+    #[serde(rename = "warrantyDate")]
+    pub(crate) warranty_date: Option<String>,
+    #[serde(rename = "appleCareId")]
+    pub(crate) apple_care_id: Option<String>,
+    #[serde(rename = "leaseDate")]
+    pub(crate) lease_date: Option<String>,
+    /// Free text in Jamf, e.g. `$1,299`
+    #[serde(rename = "purchasePrice")]
+    pub(crate) purchase_price: Option<String>,
+    #[serde(rename = "lifeExpectancy")]
+    pub(crate) life_expectancy: Option<i64>,
+    #[serde(rename = "purchasingAccount")]
+    pub(crate) purchasing_account: Option<String>,
+    #[serde(rename = "purchasingContact")]
+    pub(crate) purchasing_contact: Option<String>,
+    #[serde(rename = "extensionAttributes", default)]
+    pub(crate) extension_attributes: Vec<JamfExtensionAttribute>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerApplication {
+    pub(crate) name: Option<String>,
+    pub(crate) path: Option<String>,
+    pub(crate) version: Option<String>,
+    #[serde(rename = "macAppStore")]
+    pub(crate) mac_app_store: Option<bool>,
+    #[serde(rename = "sizeMegabytes")]
+    pub(crate) size_megabytes: Option<i64>,
+    #[serde(rename = "bundleId")]
+    pub(crate) bundle_id: Option<String>,
+    #[serde(rename = "updateAvailable")]
+    pub(crate) update_available: Option<bool>,
+    #[serde(rename = "externalVersionId")]
+    pub(crate) external_version_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerStorage {
+    #[serde(rename = "bootDriveAvailableSpaceMegabytes")]
+    pub(crate) boot_drive_available_space_megabytes: Option<i64>,
+    #[serde(default)]
+    pub(crate) disks: Vec<JamfComputerDisk>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerDisk {
+    pub(crate) id: Option<String>,
+    pub(crate) device: Option<String>,
+    pub(crate) model: Option<String>,
+    pub(crate) revision: Option<String>,
+    #[serde(rename = "serialNumber")]
+    pub(crate) serial_number: Option<String>,
+    #[serde(rename = "sizeMegabytes")]
+    pub(crate) size_megabytes: Option<i64>,
+    #[serde(rename = "smartStatus")]
+    pub(crate) smart_status: Option<String>,
+    #[serde(rename = "type")]
+    pub(crate) disk_type: Option<String>,
+    #[serde(default)]
+    pub(crate) partitions: Vec<JamfComputerPartition>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerPartition {
+    pub(crate) name: Option<String>,
+    #[serde(rename = "sizeMegabytes")]
+    pub(crate) size_megabytes: Option<i64>,
+    #[serde(rename = "availableMegabytes")]
+    pub(crate) available_megabytes: Option<i64>,
+    #[serde(rename = "partitionType")]
+    pub(crate) partition_type: Option<String>,
+    #[serde(rename = "percentUsed")]
+    pub(crate) percent_used: Option<i64>,
+    #[serde(rename = "fileVault2State")]
+    pub(crate) file_vault2_state: Option<JamfPartitionFileVault2State>,
+    #[serde(rename = "fileVault2ProgressPercent")]
+    pub(crate) file_vault2_progress_percent: Option<i64>,
+    #[serde(rename = "lvmManaged")]
+    pub(crate) lvm_managed: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerUserAndLocation {
+    pub(crate) username: Option<String>,
+    pub(crate) realname: Option<String>,
+    pub(crate) email: Option<String>,
+    pub(crate) position: Option<String>,
+    pub(crate) phone: Option<String>,
+    #[serde(rename = "departmentId")]
+    pub(crate) department_id: Option<String>,
+    #[serde(rename = "buildingId")]
+    pub(crate) building_id: Option<String>,
+    pub(crate) room: Option<String>,
+    #[serde(rename = "extensionAttributes", default)]
+    pub(crate) extension_attributes: Vec<JamfExtensionAttribute>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerConfigurationProfile {
+    pub(crate) id: Option<String>,
+    pub(crate) username: Option<String>,
+    #[serde(rename = "lastInstalled")]
+    pub(crate) last_installed: Option<String>,
+    pub(crate) removable: Option<bool>,
+    #[serde(rename = "displayName")]
+    pub(crate) display_name: Option<String>,
+    #[serde(rename = "profileIdentifier")]
+    pub(crate) profile_identifier: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerPrinter {
+    pub(crate) name: Option<String>,
+    #[serde(rename = "type")]
+    pub(crate) printer_type: Option<String>,
+    pub(crate) uri: Option<String>,
+    pub(crate) location: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerService {
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerLocalUserAccount {
+    pub(crate) uid: Option<String>,
+    #[serde(rename = "userGuid")]
+    pub(crate) user_guid: Option<String>,
+    pub(crate) username: Option<String>,
+    #[serde(rename = "fullName")]
+    pub(crate) full_name: Option<String>,
+    pub(crate) admin: Option<bool>,
+    #[serde(rename = "homeDirectory")]
+    pub(crate) home_directory: Option<String>,
+    #[serde(rename = "homeDirectorySizeMb")]
+    pub(crate) home_directory_size_mb: Option<i64>,
+    #[serde(rename = "fileVault2Enabled")]
+    pub(crate) file_vault2_enabled: Option<bool>,
+    #[serde(rename = "userAccountType")]
+    pub(crate) user_account_type: Option<String>,
+    #[serde(rename = "passwordMinLength")]
+    pub(crate) password_min_length: Option<i64>,
+    #[serde(rename = "passwordMaxAge")]
+    pub(crate) password_max_age: Option<i64>,
+    #[serde(rename = "passwordMinComplexCharacters")]
+    pub(crate) password_min_complex_characters: Option<i64>,
+    #[serde(rename = "passwordHistoryDepth")]
+    pub(crate) password_history_depth: Option<i64>,
+    #[serde(rename = "passwordRequireAlphanumeric")]
+    pub(crate) password_require_alphanumeric: Option<bool>,
+    #[serde(rename = "computerAzureActiveDirectoryId")]
+    pub(crate) computer_azure_active_directory_id: Option<String>,
+    #[serde(rename = "userAzureActiveDirectoryId")]
+    pub(crate) user_azure_active_directory_id: Option<String>,
+    #[serde(rename = "azureActiveDirectoryId")]
+    pub(crate) azure_active_directory_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerCertificate {
+    #[serde(rename = "commonName")]
+    pub(crate) common_name: Option<String>,
+    pub(crate) identity: Option<bool>,
+    #[serde(rename = "expirationDate")]
+    pub(crate) expiration_date: Option<String>,
+    pub(crate) username: Option<String>,
+    #[serde(rename = "lifecycleStatus")]
+    pub(crate) lifecycle_status: Option<String>,
+    #[serde(rename = "certificateStatus")]
+    pub(crate) certificate_status: Option<String>,
+    #[serde(rename = "subjectName")]
+    pub(crate) subject_name: Option<String>,
+    #[serde(rename = "serialNumber")]
+    pub(crate) serial_number: Option<String>,
+    #[serde(rename = "sha1Fingerprint")]
+    pub(crate) sha1_fingerprint: Option<String>,
+    #[serde(rename = "issuedDate")]
+    pub(crate) issued_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerAttachment {
+    pub(crate) id: Option<String>,
+    pub(crate) name: Option<String>,
+    #[serde(rename = "fileType")]
+    pub(crate) file_type: Option<String>,
+    #[serde(rename = "sizeBytes")]
+    pub(crate) size_bytes: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerPlugin {
+    pub(crate) name: Option<String>,
+    pub(crate) version: Option<String>,
+    pub(crate) path: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct JamfComputerInventoryResponse {
+pub(crate) struct JamfComputerPackageReceipts {
+    #[serde(rename = "installedByJamfPro", default)]
+    pub(crate) installed_by_jamf_pro: Vec<String>,
+    #[serde(rename = "installedByInstallerSwu", default)]
+    pub(crate) installed_by_installer_swu: Vec<String>,
+    #[serde(default)]
+    pub(crate) cached: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerFont {
+    pub(crate) name: Option<String>,
+    pub(crate) version: Option<String>,
+    pub(crate) path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfSipStatus {
+    NotCollected,
+    NotAvailable,
+    Disabled,
+    Enabled,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfGatekeeperStatus {
+    NotCollected,
+    Disabled,
+    AppStoreAndIdentifiedDevelopers,
+    AppStore,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfSecureBootLevel {
+    NoSecurity,
+    MediumSecurity,
+    FullSecurity,
+    NotSupported,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfExternalBootLevel {
+    AllowBootingFromExternalMedia,
+    DisallowBootingFromExternalMedia,
+    NotSupported,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfComputerSecurity {
+    #[serde(rename = "sipStatus")]
+    pub(crate) sip_status: Option<JamfSipStatus>,
+    #[serde(rename = "gatekeeperStatus")]
+    pub(crate) gatekeeper_status: Option<JamfGatekeeperStatus>,
+    #[serde(rename = "xprotectVersion")]
+    pub(crate) xprotect_version: Option<String>,
+    #[serde(rename = "autoLoginDisabled")]
+    pub(crate) auto_login_disabled: Option<bool>,
+    #[serde(rename = "remoteDesktopEnabled")]
+    pub(crate) remote_desktop_enabled: Option<bool>,
+    #[serde(rename = "activationLockEnabled")]
+    pub(crate) activation_lock_enabled: Option<bool>,
+    #[serde(rename = "recoveryLockEnabled")]
+    pub(crate) recovery_lock_enabled: Option<bool>,
+    #[serde(rename = "firewallEnabled")]
+    pub(crate) firewall_enabled: Option<bool>,
+    #[serde(rename = "secureBootLevel")]
+    pub(crate) secure_boot_level: Option<JamfSecureBootLevel>,
+    #[serde(rename = "externalBootLevel")]
+    pub(crate) external_boot_level: Option<JamfExternalBootLevel>,
+    #[serde(rename = "bootstrapTokenAllowed")]
+    pub(crate) bootstrap_token_allowed: Option<bool>,
+    #[serde(rename = "bootstrapTokenEscrowedStatus")]
+    pub(crate) bootstrap_token_escrowed_status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerLicensedSoftware {
+    pub(crate) id: Option<String>,
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerIbeacon {
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfExtensionAttributeDataType {
+    String,
+    Integer,
+    Date,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum JamfExtensionAttributeInputType {
+    Text,
+    Popup,
+    Script,
+    Ldap,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Custom inventory field defined by the Jamf admin, values are always sent as strings
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfExtensionAttribute {
+    #[serde(rename = "definitionId")]
+    pub(crate) definition_id: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) enabled: Option<bool>,
+    #[serde(rename = "multiValue")]
+    pub(crate) multi_value: Option<bool>,
+    #[serde(default)]
+    pub(crate) values: Vec<String>,
+    #[serde(rename = "dataType")]
+    pub(crate) data_type: Option<JamfExtensionAttributeDataType>,
+    #[serde(default)]
+    pub(crate) options: Vec<String>,
+    #[serde(rename = "inputType")]
+    pub(crate) input_type: Option<JamfExtensionAttributeInputType>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerContentCaching {
+    #[serde(rename = "computerContentCachingInformationId")]
+    pub(crate) computer_content_caching_information_id: Option<String>,
+    pub(crate) activated: Option<bool>,
+    pub(crate) active: Option<bool>,
+    #[serde(rename = "actualCacheBytesUsed")]
+    pub(crate) actual_cache_bytes_used: Option<i64>,
+    #[serde(rename = "cacheBytesFree")]
+    pub(crate) cache_bytes_free: Option<i64>,
+    #[serde(rename = "cacheBytesLimit")]
+    pub(crate) cache_bytes_limit: Option<i64>,
+    #[serde(rename = "cacheStatus")]
+    pub(crate) cache_status: Option<String>,
+    #[serde(rename = "cacheBytesUsed")]
+    pub(crate) cache_bytes_used: Option<i64>,
+    #[serde(rename = "dataMigrationCompleted")]
+    pub(crate) data_migration_completed: Option<bool>,
+    pub(crate) port: Option<i64>,
+    #[serde(rename = "publicAddress")]
+    pub(crate) public_address: Option<String>,
+    #[serde(rename = "registrationStatus")]
+    pub(crate) registration_status: Option<String>,
+    #[serde(rename = "restrictedMedia")]
+    pub(crate) restricted_media: Option<bool>,
+    #[serde(rename = "serverGuid")]
+    pub(crate) server_guid: Option<String>,
+    #[serde(rename = "startupStatus")]
+    pub(crate) startup_status: Option<String>,
+    #[serde(rename = "tetheratorStatus")]
+    pub(crate) tetherator_status: Option<String>,
+    #[serde(rename = "totalBytesAreSince")]
+    pub(crate) total_bytes_are_since: Option<String>,
+    #[serde(rename = "totalBytesDropped")]
+    pub(crate) total_bytes_dropped: Option<i64>,
+    #[serde(rename = "totalBytesImported")]
+    pub(crate) total_bytes_imported: Option<i64>,
+    #[serde(rename = "totalBytesReturnedToChildren")]
+    pub(crate) total_bytes_returned_to_children: Option<i64>,
+    #[serde(rename = "totalBytesReturnedToClients")]
+    pub(crate) total_bytes_returned_to_clients: Option<i64>,
+    #[serde(rename = "totalBytesReturnedToPeers")]
+    pub(crate) total_bytes_returned_to_peers: Option<i64>,
+    #[serde(rename = "totalBytesStoredFromOrigin")]
+    pub(crate) total_bytes_stored_from_origin: Option<i64>,
+    #[serde(rename = "totalBytesStoredFromParents")]
+    pub(crate) total_bytes_stored_from_parents: Option<i64>,
+    #[serde(rename = "totalBytesStoredFromPeers")]
+    pub(crate) total_bytes_stored_from_peers: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfComputerGroupMembership {
+    #[serde(rename = "groupId")]
+    pub(crate) group_id: Option<String>,
+    #[serde(rename = "groupName")]
+    pub(crate) group_name: Option<String>,
+    #[serde(rename = "smartGroup")]
+    pub(crate) smart_group: Option<bool>,
+}
+
+/// A page of one of Jamf's inventory endpoints
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfInventoryResponse<T> {
     #[serde(rename = "totalCount")]
     pub(crate) total_count: usize,
-    pub(crate) results: Vec<JamfComputer>,
+    pub(crate) results: Vec<T>,
+}
+
+pub(crate) type JamfComputerInventoryResponse = JamfInventoryResponse<JamfComputer>;
+
+pub(crate) type JamfMobileDeviceInventoryResponse = JamfInventoryResponse<JamfMobileDevice>;
+
+/// A mobile device from mobile device inventory, only the sections that were requested are set
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfMobileDevice {
+    #[serde(rename = "mobileDeviceId")]
+    pub(crate) mobile_device_id: Option<String>,
+    /// `iOS` (iPhones and iPads), `tvOS` or `watchOS`
+    #[serde(rename = "deviceType")]
+    pub(crate) device_type: Option<String>,
+    pub(crate) general: Option<JamfMobileDeviceGeneral>,
+    pub(crate) hardware: Option<JamfMobileDeviceHardware>,
+    #[serde(rename = "userAndLocation")]
+    pub(crate) user_and_location: Option<JamfMobileDeviceLocation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfMobileDeviceGeneral {
+    pub(crate) udid: Option<String>,
+    #[serde(rename = "displayName")]
+    pub(crate) display_name: Option<String>,
+    #[serde(rename = "assetTag")]
+    pub(crate) asset_tag: Option<String>,
+    #[serde(rename = "lastInventoryUpdateDate")]
+    pub(crate) last_inventory_update_date: Option<String>,
+    #[serde(rename = "osVersion")]
+    pub(crate) os_version: Option<String>,
+    #[serde(rename = "osBuild")]
+    pub(crate) os_build: Option<String>,
+    #[serde(rename = "osRapidSecurityResponse")]
+    pub(crate) os_rapid_security_response: Option<String>,
+    pub(crate) managed: Option<bool>,
+    pub(crate) supervised: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct JamfMobileDeviceHardware {
+    #[serde(rename = "serialNumber")]
+    pub(crate) serial_number: Option<String>,
+    pub(crate) model: Option<String>,
+    #[serde(rename = "modelIdentifier")]
+    pub(crate) model_identifier: Option<String>,
+    #[serde(rename = "batteryLevel")]
+    pub(crate) battery_level: Option<i64>,
+    #[serde(rename = "capacityMb")]
+    pub(crate) capacity_mb: Option<i64>,
+    #[serde(rename = "availableSpaceMb")]
+    pub(crate) available_space_mb: Option<i64>,
+}
+
+/// A single mobile device from `/api/v2/mobile-devices/{id}/detail`
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfMobileDeviceDetail {
+    pub(crate) id: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) udid: Option<String>,
+    #[serde(rename = "serialNumber")]
+    pub(crate) serial_number: Option<String>,
+    #[serde(rename = "assetTag")]
+    pub(crate) asset_tag: Option<String>,
+    #[serde(rename = "lastInventoryUpdateTimestamp")]
+    pub(crate) last_inventory_update_timestamp: Option<String>,
+    #[serde(rename = "osVersion")]
+    pub(crate) os_version: Option<String>,
+    #[serde(rename = "osBuild")]
+    pub(crate) os_build: Option<String>,
+    #[serde(rename = "osRapidSecurityResponse")]
+    pub(crate) os_rapid_security_response: Option<String>,
+    pub(crate) managed: Option<bool>,
+    /// `ios`, `appleTv` or `unknown`
+    #[serde(rename = "type")]
+    pub(crate) device_type: Option<String>,
+    pub(crate) location: Option<JamfMobileDeviceLocation>,
+    pub(crate) ios: Option<JamfMobileDeviceIos>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfMobileDeviceLocation {
+    pub(crate) username: Option<String>,
+    #[serde(rename = "realName")]
+    pub(crate) real_name: Option<String>,
+    #[serde(rename = "emailAddress")]
+    pub(crate) email_address: Option<String>,
+    pub(crate) position: Option<String>,
+    #[serde(rename = "phoneNumber")]
+    pub(crate) phone_number: Option<String>,
+    #[serde(rename = "departmentId")]
+    pub(crate) department_id: Option<String>,
+    #[serde(rename = "buildingId")]
+    pub(crate) building_id: Option<String>,
+    pub(crate) room: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfMobileDeviceIos {
+    pub(crate) model: Option<String>,
+    #[serde(rename = "modelIdentifier")]
+    pub(crate) model_identifier: Option<String>,
+    #[serde(rename = "modelNumber")]
+    pub(crate) model_number: Option<String>,
+    pub(crate) supervised: Option<bool>,
+    #[serde(rename = "batteryLevel")]
+    pub(crate) battery_level: Option<i64>,
+    #[serde(rename = "capacityMb")]
+    pub(crate) capacity_mb: Option<i64>,
+    #[serde(rename = "availableMb")]
+    pub(crate) available_mb: Option<i64>,
+    pub(crate) shared: Option<bool>,
+    pub(crate) security: Option<JamfMobileDeviceSecurity>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct JamfMobileDeviceSecurity {
+    #[serde(rename = "dataProtected")]
+    pub(crate) data_protected: Option<bool>,
+    #[serde(rename = "hardwareEncryption")]
+    pub(crate) hardware_encryption: Option<i64>,
+    #[serde(rename = "passcodePresent")]
+    pub(crate) passcode_present: Option<bool>,
+    #[serde(rename = "passcodeCompliant")]
+    pub(crate) passcode_compliant: Option<bool>,
+    #[serde(rename = "activationLockEnabled")]
+    pub(crate) activation_lock_enabled: Option<bool>,
+    #[serde(rename = "jailBreakDetected")]
+    pub(crate) jail_break_detected: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -71,3 +881,228 @@ pub(crate) struct AvailableUpdates {
     #[serde(rename = "iOS")]
     pub(crate) ios: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        JamfComputerInventoryResponse, JamfExtensionAttributeDataType,
+        JamfExtensionAttributeInputType, JamfFileVault2Status, JamfGatekeeperStatus,
+        JamfMobileDeviceDetail, JamfMobileDeviceInventoryResponse, JamfPartitionFileVault2State,
+        JamfSecureBootLevel, JamfSipStatus,
+    };
+
+    // Sample payload with every section for two computers, in the shape Jamf Pro 11.3 returns
+    static COMPUTERS_INVENTORY: &str = include_str!("testdata/computers_inventory.json");
+    // Sample payloads for an iPhone, an iPad and an Apple TV, same Jamf version
+    static MOBILE_DEVICES_INVENTORY: &str = include_str!("testdata/mobile_devices_inventory.json");
+    static MOBILE_DEVICE_DETAIL: &str = include_str!("testdata/mobile_device_detail.json");
+
+    fn inventory() -> JamfComputerInventoryResponse {
+        serde_json::from_str(COMPUTERS_INVENTORY).unwrap()
+    }
+
+    #[test]
+    fn every_section_deserialized() {
+        let inventory = inventory();
+        assert_eq!(inventory.total_count, 2);
+        let computer = &inventory.results[0];
+
+        let general = computer.general.as_ref().unwrap();
+        assert_eq!(general.name, "peter’s MacBook Air");
+        assert_eq!(
+            general.last_contact_time.as_deref(),
+            Some("2024-05-03T08:02:11.212Z")
+        );
+        assert_eq!(
+            general.mdm_capable.as_ref().unwrap().capable_users,
+            ["peter"]
+        );
+
+        let hardware = computer.hardware.as_ref().unwrap();
+        assert_eq!(hardware.serial_number.as_deref(), Some("FVFDK0XJQ6L4"));
+        assert_eq!(hardware.apple_silicon, Some(true));
+
+        let os = computer.operating_system.as_ref().unwrap();
+        assert_eq!(os.version, "14.4.1");
+        assert_eq!(
+            os.file_vault2_status,
+            Some(JamfFileVault2Status::BootEncrypted)
+        );
+
+        let disk_encryption = computer.disk_encryption.as_ref().unwrap();
+        let boot = disk_encryption
+            .boot_partition_encryption_details
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            boot.partition_file_vault2_state,
+            Some(JamfPartitionFileVault2State::Encrypted)
+        );
+        assert_eq!(disk_encryption.file_vault2_enabled_user_names, ["peter"]);
+
+        let security = computer.security.as_ref().unwrap();
+        assert_eq!(security.sip_status, Some(JamfSipStatus::Enabled));
+        assert_eq!(
+            security.gatekeeper_status,
+            Some(JamfGatekeeperStatus::AppStoreAndIdentifiedDevelopers)
+        );
+        assert_eq!(
+            security.secure_boot_level,
+            Some(JamfSecureBootLevel::FullSecurity)
+        );
+        assert_eq!(security.firewall_enabled, Some(true));
+        assert_eq!(security.xprotect_version.as_deref(), Some("2193"));
+
+        let applications = computer.applications.as_ref().unwrap();
+        assert_eq!(applications.len(), 2);
+        assert_eq!(
+            applications[1].bundle_id.as_deref(),
+            Some("com.tinyspeck.slackmacgap")
+        );
+
+        let user = computer.user_and_location.as_ref().unwrap();
+        assert_eq!(user.email.as_deref(), Some("peter@example.com"));
+
+        let purchasing = computer.purchasing.as_ref().unwrap();
+        assert_eq!(purchasing.purchase_price.as_deref(), Some("$1,299"));
+
+        let storage = computer.storage.as_ref().unwrap();
+        assert_eq!(storage.disks[0].partitions[0].percent_used, Some(38));
+
+        let attributes = computer.extension_attributes.as_ref().unwrap();
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(
+            attributes[1].data_type,
+            Some(JamfExtensionAttributeDataType::Integer)
+        );
+        assert_eq!(
+            attributes[2].input_type,
+            Some(JamfExtensionAttributeInputType::Popup)
+        );
+        assert!(attributes[2].values.is_empty());
+
+        assert_eq!(
+            computer.local_user_accounts.as_ref().unwrap()[0].admin,
+            Some(true)
+        );
+        assert_eq!(
+            computer.certificates.as_ref().unwrap()[0]
+                .expiration_date
+                .as_deref(),
+            Some("2033-11-14T16:45:00Z")
+        );
+        assert_eq!(
+            computer.configuration_profiles.as_ref().unwrap()[0]
+                .profile_identifier
+                .as_deref(),
+            Some("com.example.filevault")
+        );
+        assert_eq!(
+            computer
+                .package_receipts
+                .as_ref()
+                .unwrap()
+                .installed_by_jamf_pro,
+            ["CompanyPortal-1.2.pkg"]
+        );
+        assert_eq!(
+            computer.software_updates.as_ref().unwrap()[0].version,
+            "14.5"
+        );
+        assert_eq!(
+            computer.group_memberships.as_ref().unwrap()[0].smart_group,
+            Some(true)
+        );
+        assert!(computer.content_caching.is_some());
+        for section in [
+            computer.printers.as_ref().map(Vec::len),
+            computer.services.as_ref().map(Vec::len),
+            computer.attachments.as_ref().map(Vec::len),
+            computer.plugins.as_ref().map(Vec::len),
+            computer.fonts.as_ref().map(Vec::len),
+            computer.licensed_software.as_ref().map(Vec::len),
+            computer.ibeacons.as_ref().map(Vec::len),
+        ] {
+            assert_eq!(section, Some(1));
+        }
+    }
+
+    #[test]
+    fn missing_and_null_fields_tolerated() {
+        let inventory = inventory();
+        let computer = &inventory.results[1];
+
+        let general = computer.general.as_ref().unwrap();
+        assert_eq!(general.name, "cw-zsn-mac-1");
+        assert!(general.report_date.is_none());
+        assert!(general.extension_attributes.is_empty());
+        assert!(computer.purchasing.is_none());
+        assert!(computer.content_caching.is_none());
+        assert!(computer
+            .package_receipts
+            .as_ref()
+            .unwrap()
+            .cached
+            .is_empty());
+        assert!(computer.hardware.as_ref().unwrap().serial_number.is_none());
+        assert_eq!(
+            computer
+                .operating_system
+                .as_ref()
+                .unwrap()
+                .file_vault2_status,
+            Some(JamfFileVault2Status::NotEncrypted)
+        );
+    }
+
+    #[test]
+    fn unknown_statuses_tolerated() {
+        let inventory = inventory();
+        let security = inventory.results[1].security.as_ref().unwrap();
+        assert_eq!(security.sip_status, Some(JamfSipStatus::NotCollected));
+        assert_eq!(
+            security.secure_boot_level,
+            Some(JamfSecureBootLevel::Unknown)
+        );
+        assert!(security.external_boot_level.is_none());
+    }
+
+    #[test]
+    fn unrequested_sections_left_out() {
+        let inventory: JamfComputerInventoryResponse = serde_json::from_str(
+            r#"{"totalCount":1,"results":[{"id":"12","udid":"x","general":{"name":"mac"}}]}"#,
+        )
+        .unwrap();
+        let computer = &inventory.results[0];
+        assert!(computer.general.is_some());
+        assert!(computer.hardware.is_none());
+        assert!(computer.security.is_none());
+        assert!(computer.applications.is_none());
+        assert!(computer.extension_attributes.is_none());
+    }
+
+    #[test]
+    fn mobile_devices_deserialized() {
+        let inventory: JamfMobileDeviceInventoryResponse =
+            serde_json::from_str(MOBILE_DEVICES_INVENTORY).unwrap();
+        assert_eq!(inventory.total_count, 3);
+        let ipad = &inventory.results[1];
+        assert_eq!(ipad.device_type.as_deref(), Some("iOS"));
+        assert_eq!(
+            ipad.hardware.as_ref().unwrap().model_identifier.as_deref(),
+            Some("iPad13,16")
+        );
+        let apple_tv = &inventory.results[2];
+        assert!(apple_tv.general.as_ref().unwrap().asset_tag.is_none());
+
+        let detail: JamfMobileDeviceDetail = serde_json::from_str(MOBILE_DEVICE_DETAIL).unwrap();
+        assert_eq!(detail.device_type.as_deref(), Some("ios"));
+        assert_eq!(
+            detail.location.unwrap().email_address.as_deref(),
+            Some("peter@example.com")
+        );
+        let security = detail.ios.unwrap().security.unwrap();
+        assert_eq!(security.hardware_encryption, Some(3));
+        assert_eq!(security.jail_break_detected, Some(false));
+    }
+}
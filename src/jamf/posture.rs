@@ -0,0 +1,475 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::env_or,
+    models::{
+        JamfComputerDiskEncryption, JamfComputerSecurity, JamfComputerStorage,
+        JamfFileVault2Status, JamfGatekeeperStatus, JamfPartitionFileVault2State,
+        JamfRecoveryKeyValidity, JamfSecureBootLevel, JamfSipStatus,
+    },
+};
+
+/// FileVault state of a Mac, the evidence behind `disk_encrypted`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct DiskEncryption {
+    file_vault2_status: Option<JamfFileVault2Status>,
+    /// Whether the personal recovery key escrowed in Jamf still unlocks the disk
+    recovery_key_validity: Option<JamfRecoveryKeyValidity>,
+    institutional_recovery_key_present: Option<bool>,
+    enabled_users: Vec<String>,
+    partitions: Vec<PartitionEncryption>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct PartitionEncryption {
+    name: Option<String>,
+    boot: bool,
+    state: Option<JamfPartitionFileVault2State>,
+    percent: Option<i64>,
+}
+
+impl DiskEncryption {
+    /// Whether the boot volume is encrypted, unknown if Jamf doesn't say
+    pub(crate) fn is_encrypted(&self) -> Option<bool> {
+        match self.file_vault2_status {
+            Some(JamfFileVault2Status::BootEncrypted | JamfFileVault2Status::AllEncrypted) => {
+                Some(true)
+            }
+            Some(JamfFileVault2Status::NotEncrypted) => Some(false),
+            // Other partitions being encrypted says nothing about the boot volume, ask it directly
+            _ => match self.partitions.iter().find(|p| p.boot)?.state? {
+                JamfPartitionFileVault2State::Encrypted => Some(true),
+                JamfPartitionFileVault2State::Unknown | JamfPartitionFileVault2State::Other => None,
+                // Still encrypting doesn't count yet
+                _ => Some(false),
+            },
+        }
+    }
+
+    fn has_invalid_recovery_key(&self) -> bool {
+        self.recovery_key_validity == Some(JamfRecoveryKeyValidity::Invalid)
+    }
+}
+
+/// Gather the FileVault state from the disk encryption and storage sections
+/// The overall FileVault status is reported in the operating system section
+/// Every partition comes from storage, without it we only know the boot partition
+pub(crate) fn disk_encryption(
+    disk_encryption: Option<JamfComputerDiskEncryption>,
+    storage: Option<JamfComputerStorage>,
+    file_vault2_status: Option<JamfFileVault2Status>,
+) -> Option<DiskEncryption> {
+    if disk_encryption.is_none() && storage.is_none() && file_vault2_status.is_none() {
+        return None;
+    }
+    let mut partitions = storage
+        .into_iter()
+        .flat_map(|s| s.disks)
+        .flat_map(|d| d.partitions)
+        .map(|p| PartitionEncryption {
+            boot: p.partition_type.as_deref() == Some("BOOT"),
+            name: p.name,
+            state: p.file_vault2_state,
+            percent: p.file_vault2_progress_percent,
+        })
+        .collect::<Vec<_>>();
+    let disk_encryption = disk_encryption.unwrap_or_default();
+    if !partitions.iter().any(|p| p.boot) {
+        if let Some(boot) = disk_encryption.boot_partition_encryption_details {
+            partitions.insert(
+                0,
+                PartitionEncryption {
+                    name: boot.partition_name,
+                    boot: true,
+                    state: boot.partition_file_vault2_state,
+                    percent: boot.partition_file_vault2_percent,
+                },
+            );
+        }
+    }
+
+    Some(DiskEncryption {
+        file_vault2_status,
+        recovery_key_validity: disk_encryption.individual_recovery_key_validity_status,
+        institutional_recovery_key_present: disk_encryption.institutional_recovery_key_present,
+        enabled_users: disk_encryption.file_vault2_enabled_user_names,
+        partitions,
+    })
+}
+
+/// How many Macs in a device list are encrypted, for SOC 2 evidence
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct DiskEncryptionSummary {
+    encrypted: usize,
+    not_encrypted: usize,
+    unknown: usize,
+    /// Macs whose escrowed recovery key doesn't work, IT couldn't unlock them
+    invalid_recovery_keys: usize,
+}
+
+impl DiskEncryptionSummary {
+    pub(crate) fn add(&mut self, disk_encryption: Option<&DiskEncryption>) {
+        match disk_encryption.and_then(DiskEncryption::is_encrypted) {
+            Some(true) => self.encrypted += 1,
+            Some(false) => self.not_encrypted += 1,
+            None => self.unknown += 1,
+        }
+        if disk_encryption.is_some_and(DiskEncryption::has_invalid_recovery_key) {
+            self.invalid_recovery_keys += 1;
+        }
+    }
+}
+
+/// Outcome of a single security check, `unknown` when Jamf didn't collect what it needs
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckResult {
+    Pass,
+    Fail,
+    #[default]
+    Unknown,
+}
+
+impl From<Option<bool>> for CheckResult {
+    fn from(passed: Option<bool>) -> Self {
+        match passed {
+            Some(true) => CheckResult::Pass,
+            Some(false) => CheckResult::Fail,
+            None => CheckResult::Unknown,
+        }
+    }
+}
+
+impl From<CheckResult> for Option<bool> {
+    fn from(result: CheckResult) -> Self {
+        match result {
+            CheckResult::Pass => Some(true),
+            CheckResult::Fail => Some(false),
+            CheckResult::Unknown => None,
+        }
+    }
+}
+
+/// The macOS protections we expect on every Mac, from Jamf's security section
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct SecurityChecks {
+    /// System Integrity Protection
+    sip: CheckResult,
+    /// Gatekeeper allows apps from the App Store, optionally also from identified developers
+    gatekeeper: CheckResult,
+    /// The application firewall
+    firewall: CheckResult,
+    /// XProtect can't be turned off, Jamf reporting its version shows it's installed
+    xprotect: CheckResult,
+    /// Secure boot at full or medium security, Macs without a T2 or Apple silicon fail
+    secure_boot: CheckResult,
+}
+
+impl SecurityChecks {
+    /// The failure each check reports, with whether it passed, for the compliance verdict
+    pub(crate) fn compliance_checks(&self) -> [(&'static str, Option<bool>); 5] {
+        [
+            ("sip_disabled", self.sip.into()),
+            ("gatekeeper_disabled", self.gatekeeper.into()),
+            ("firewall_disabled", self.firewall.into()),
+            ("xprotect_missing", self.xprotect.into()),
+            ("secure_boot_disabled", self.secure_boot.into()),
+        ]
+    }
+}
+
+/// Run the security checks, all unknown if Jamf didn't report the security section
+pub(crate) fn security_checks(security: Option<&JamfComputerSecurity>) -> SecurityChecks {
+    let Some(security) = security else {
+        return SecurityChecks::default();
+    };
+    SecurityChecks {
+        sip: match security.sip_status {
+            Some(JamfSipStatus::Enabled) => CheckResult::Pass,
+            Some(JamfSipStatus::Disabled) => CheckResult::Fail,
+            _ => CheckResult::Unknown,
+        },
+        gatekeeper: match security.gatekeeper_status {
+            Some(
+                JamfGatekeeperStatus::AppStore
+                | JamfGatekeeperStatus::AppStoreAndIdentifiedDevelopers,
+            ) => CheckResult::Pass,
+            Some(JamfGatekeeperStatus::Disabled) => CheckResult::Fail,
+            _ => CheckResult::Unknown,
+        },
+        firewall: security.firewall_enabled.into(),
+        xprotect: match security.xprotect_version.as_deref() {
+            Some(version) if !version.is_empty() => CheckResult::Pass,
+            _ => CheckResult::Unknown,
+        },
+        secure_boot: match security.secure_boot_level {
+            Some(JamfSecureBootLevel::FullSecurity | JamfSecureBootLevel::MediumSecurity) => {
+                CheckResult::Pass
+            }
+            Some(JamfSecureBootLevel::NoSecurity | JamfSecureBootLevel::NotSupported) => {
+                CheckResult::Fail
+            }
+            _ => CheckResult::Unknown,
+        },
+    }
+}
+
+/// Whether a device passes our compliance checks
+/// `compliant` is unknown when Jamf didn't report enough to run every check
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ComplianceVerdict {
+    pub(crate) compliant: Option<bool>,
+    /// Checks the device failed, e.g. `os_outdated`
+    pub(crate) failures: Vec<String>,
+}
+
+/// Each check is the failure it reports and whether it passed, `None` if it couldn't run
+pub(crate) fn compliance_verdict(checks: &[(&str, Option<bool>)]) -> ComplianceVerdict {
+    let failures = checks
+        .iter()
+        .filter(|(_, passed)| *passed == Some(false))
+        .map(|(failure, _)| failure.to_string())
+        .collect::<Vec<_>>();
+    ComplianceVerdict {
+        // A failed check decides the verdict even if others couldn't run
+        compliant: if !failures.is_empty() {
+            Some(false)
+        } else if checks.iter().any(|(_, passed)| passed.is_none()) {
+            None
+        } else {
+            Some(true)
+        },
+        failures,
+    }
+}
+
+/// The verdict for a Mac, from its OS, FileVault and security checks and when it last checked in
+pub(crate) fn computer_compliance(
+    os_is_latest: Option<bool>,
+    disk_encrypted: Option<bool>,
+    security_checks: &SecurityChecks,
+    check_in: &CheckIn,
+) -> ComplianceVerdict {
+    let mut checks = vec![
+        ("os_outdated", os_is_latest),
+        ("disk_not_encrypted", disk_encrypted),
+    ];
+    checks.extend(security_checks.compliance_checks());
+    checks.push(check_in.compliance_check());
+    compliance_verdict(&checks)
+}
+
+/// How long a device can go without checking in before what it reports is considered outdated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StalenessConfig {
+    pub stale_after_days: i64,
+}
+
+impl Default for StalenessConfig {
+    fn default() -> Self {
+        Self {
+            stale_after_days: 30,
+        }
+    }
+}
+
+impl StalenessConfig {
+    /// Read the threshold from `JAMF_STALE_AFTER_DAYS`, falling back to the default
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            stale_after_days: env_or("JAMF_STALE_AFTER_DAYS", default.stale_after_days).max(1),
+        }
+    }
+}
+
+/// When a device last talked to Jamf
+/// A device that hasn't in a while still reports its old OS version and settings, so its
+/// compliance verdict can't be trusted
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct CheckIn {
+    /// Last time the device contacted Jamf
+    last_contact_time: Option<DateTime<Utc>>,
+    /// Last time the device sent an inventory report
+    report_date: Option<DateTime<Utc>>,
+    /// Whole days since the later of the two, unknown if Jamf has neither
+    days_since_check_in: Option<i64>,
+    /// Not checked in for more than the configured number of days
+    stale: bool,
+}
+
+impl CheckIn {
+    pub(crate) fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// A stale device fails compliance, the rest of its checks ran on what it reported back then
+    pub(crate) fn compliance_check(&self) -> (&'static str, Option<bool>) {
+        ("stale_check_in", Some(!self.stale))
+    }
+}
+
+// Jamf sends RFC 3339 timestamps, anything else is treated as missing
+fn parse_timestamp(timestamp: Option<&str>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp?)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+pub(crate) fn check_in(
+    last_contact_time: Option<&str>,
+    report_date: Option<&str>,
+    staleness: StalenessConfig,
+    now: DateTime<Utc>,
+) -> CheckIn {
+    let last_contact_time = parse_timestamp(last_contact_time);
+    let report_date = parse_timestamp(report_date);
+    // Sending a report is contact too, and a clock running ahead of ours doesn't count as negative days
+    let days_since_check_in = last_contact_time
+        .max(report_date)
+        .map(|checked_in| (now - checked_in).num_days().max(0));
+    CheckIn {
+        last_contact_time,
+        report_date,
+        days_since_check_in,
+        stale: days_since_check_in.is_some_and(|days| days > staleness.stale_after_days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jamf::models::{
+        JamfComputerDiskEncryption, JamfComputerInventoryResponse, JamfFileVault2Status,
+        JamfPartitionEncryption, JamfPartitionFileVault2State,
+    };
+
+    use super::{
+        check_in, disk_encryption, security_checks, CheckResult, DiskEncryptionSummary,
+        StalenessConfig,
+    };
+
+    static COMPUTERS_INVENTORY: &str = include_str!("testdata/computers_inventory.json");
+
+    #[test]
+    fn security_checks_from_inventory() {
+        let inventory: JamfComputerInventoryResponse =
+            serde_json::from_str(COMPUTERS_INVENTORY).unwrap();
+        let checks = security_checks(inventory.results[0].security.as_ref());
+        assert!(checks
+            .compliance_checks()
+            .iter()
+            .all(|(_, passed)| *passed == Some(true)));
+
+        // Not collected and levels from a newer Jamf are unknown, not failures
+        let checks = security_checks(inventory.results[1].security.as_ref());
+        assert_eq!(checks.sip, CheckResult::Unknown);
+        assert_eq!(checks.gatekeeper, CheckResult::Unknown);
+        assert_eq!(checks.firewall, CheckResult::Fail);
+        assert_eq!(checks.xprotect, CheckResult::Unknown);
+        assert_eq!(checks.secure_boot, CheckResult::Unknown);
+
+        assert_eq!(security_checks(None).firewall, CheckResult::Unknown);
+    }
+
+    #[test]
+    fn encryption_from_inventory() {
+        let inventory: JamfComputerInventoryResponse =
+            serde_json::from_str(COMPUTERS_INVENTORY).unwrap();
+        let mut summary = DiskEncryptionSummary::default();
+        let disk_encryptions = inventory
+            .results
+            .into_iter()
+            .map(|c| {
+                disk_encryption(
+                    c.disk_encryption,
+                    c.storage,
+                    c.operating_system.and_then(|o| o.file_vault2_status),
+                )
+            })
+            .collect::<Vec<_>>();
+        for disk_encryption in &disk_encryptions {
+            summary.add(disk_encryption.as_ref());
+        }
+
+        let encrypted = disk_encryptions[0].as_ref().unwrap();
+        assert_eq!(encrypted.is_encrypted(), Some(true));
+        assert_eq!(encrypted.partitions.len(), 1);
+        assert!(encrypted.partitions[0].boot);
+        // Without storage the boot partition comes from the disk encryption section
+        let not_encrypted = disk_encryptions[1].as_ref().unwrap();
+        assert_eq!(not_encrypted.is_encrypted(), Some(false));
+        assert_eq!(
+            not_encrypted.partitions[0].state,
+            Some(JamfPartitionFileVault2State::Unencrypted)
+        );
+
+        assert_eq!(summary.encrypted, 1);
+        assert_eq!(summary.not_encrypted, 1);
+        assert_eq!(summary.invalid_recovery_keys, 0);
+    }
+
+    #[test]
+    fn boot_partition_decides_when_status_does_not() {
+        let with_boot_state = |status, state| {
+            disk_encryption(
+                Some(JamfComputerDiskEncryption {
+                    boot_partition_encryption_details: Some(JamfPartitionEncryption {
+                        partition_name: Some("Macintosh HD".to_string()),
+                        partition_file_vault2_state: state,
+                        partition_file_vault2_percent: None,
+                    }),
+                    ..Default::default()
+                }),
+                None,
+                status,
+            )
+            .unwrap()
+            .is_encrypted()
+        };
+        assert_eq!(
+            with_boot_state(
+                Some(JamfFileVault2Status::SomeEncrypted),
+                Some(JamfPartitionFileVault2State::Encrypted)
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            with_boot_state(None, Some(JamfPartitionFileVault2State::Encrypting)),
+            Some(false)
+        );
+        assert_eq!(
+            with_boot_state(
+                Some(JamfFileVault2Status::Unknown),
+                Some(JamfPartitionFileVault2State::Other)
+            ),
+            None
+        );
+        assert_eq!(with_boot_state(None, None), None);
+        assert!(disk_encryption(None, None, None).is_none());
+    }
+
+    #[test]
+    fn stale_after_threshold() {
+        let now = "2024-06-30T12:00:00Z".parse().unwrap();
+        let staleness = StalenessConfig {
+            stale_after_days: 30,
+        };
+        let recent = check_in(
+            Some("2024-04-01T08:00:00.123Z"),
+            Some("2024-06-29T18:00:00Z"),
+            staleness,
+            now,
+        );
+        assert_eq!(recent.days_since_check_in, Some(0));
+        assert!(!recent.is_stale());
+
+        let stale = check_in(Some("2024-05-01T08:00:00Z"), None, staleness, now);
+        assert_eq!(stale.days_since_check_in, Some(60));
+        assert!(stale.is_stale());
+
+        let unknown = check_in(None, Some("yesterday"), staleness, now);
+        assert_eq!(unknown.days_since_check_in, None);
+        assert!(!unknown.is_stale());
+    }
+}
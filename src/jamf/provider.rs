@@ -1,42 +1,175 @@
 use super::{
-    client::{JamfClient, JamfClientError},
-    models::JamfComputer,
+    applications::ApplicationInventory,
+    client::{JamfClient, JamfClientError, MobileDeviceSection},
+    detail::{
+        assigned_user, convert_jamf_computer_detail, convert_jamf_mobile_device_detail,
+        ComputerDetail, MobileDeviceDetail, UserDetail,
+    },
+    extension_attributes::{
+        ExtensionAttributeFilter, ExtensionAttributeValue, ExtensionAttributesConfig,
+    },
+    models::{JamfComputer, JamfMobileDevice},
+    posture::{
+        check_in, compliance_verdict, computer_compliance, disk_encryption, security_checks,
+        CheckIn, ComplianceVerdict, DiskEncryption, DiskEncryptionSummary, SecurityChecks,
+        StalenessConfig,
+    },
+    query::{ComputerInventoryQuery, MobileDeviceInventoryQuery},
 };
 use crate::jamf::client::ComputerInventorySection;
 use crate::jamf::client::JamfClientTrait;
+use chrono::{DateTime, Utc};
+use futures::{stream, stream::BoxStream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
 use tracing::error;
 use version_compare::{compare, Cmp};
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
 pub struct DevicesOutput {
-    devices: Vec<Computer>,
+    devices: Vec<Device>,
+    summary: DevicesSummary,
+    /// Set when Jamf is unavailable and these are the devices from the last successful fetch
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stale: bool,
+    /// When a stale list was fetched from Jamf
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fetched_at: Option<DateTime<Utc>>,
 }
 
-fn is_os_updated(os_version: String, available_updates: Vec<String>) -> bool {
+/// Totals over a device list
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct DevicesSummary {
+    computers: usize,
+    mobile_devices: usize,
+    /// FileVault on the computers, mobile devices are always encrypted
+    disk_encryption: DiskEncryptionSummary,
+    /// Devices that haven't checked in with Jamf for longer than the configured threshold
+    stale_devices: usize,
+}
+
+impl DevicesSummary {
+    fn add(&mut self, device: &Device) {
+        if device.check_in().is_stale() {
+            self.stale_devices += 1;
+        }
+        match device {
+            Device::Computer(computer) => {
+                self.computers += 1;
+                self.disk_encryption.add(computer.disk_encryption.as_ref());
+            }
+            Device::MobileDevice(_) => self.mobile_devices += 1,
+        }
+    }
+}
+
+impl DevicesOutput {
+    fn new(devices: Vec<Device>) -> Self {
+        let mut summary = DevicesSummary::default();
+        for device in &devices {
+            summary.add(device);
+        }
+        Self {
+            devices,
+            summary,
+            stale: false,
+            fetched_at: None,
+        }
+    }
+
+    /// Add mobile devices after the computers
+    pub(crate) fn with_mobile_devices(mut self, mobile_devices: Vec<MobileDevice>) -> Self {
+        for mobile_device in mobile_devices {
+            let mobile_device = Device::MobileDevice(mobile_device);
+            self.summary.add(&mobile_device);
+            self.devices.push(mobile_device);
+        }
+        self
+    }
+
+    /// Only the computers matching every filter, mobile devices have no extension attributes so
+    /// they never match
+    pub(crate) fn filter_extension_attributes(self, filters: &[ExtensionAttributeFilter]) -> Self {
+        if filters.is_empty() {
+            return self;
+        }
+        Self {
+            stale: self.stale,
+            fetched_at: self.fetched_at,
+            ..Self::new(
+                self.devices
+                    .into_iter()
+                    .filter(|device| device.matches_extension_attributes(filters))
+                    .collect(),
+            )
+        }
+    }
+
+    /// Mark a cached list as stale, so callers know Jamf couldn't be asked
+    pub(crate) fn into_stale(self, fetched_at: DateTime<Utc>) -> Self {
+        Self {
+            stale: true,
+            fetched_at: Some(fetched_at),
+            ..self
+        }
+    }
+}
+
+/// Whether `version` is lower than `other`, `None` if either isn't a version we can compare
+pub(crate) fn is_version_below(version: &str, other: &str) -> Option<bool> {
+    compare(version, other).ok().map(|cmp| cmp == Cmp::Lt)
+}
+
+pub(crate) fn is_os_updated(os_version: String, available_updates: Vec<String>) -> bool {
     // NOTE: There are probably different ways to interpret an OS being out of date
     //  here we just assume anything lower than the highest version in the list of available updates
     //  is out of date
     !available_updates
         .iter()
-        .any(|update| compare(os_version.clone(), update).unwrap_or(Cmp::Lt) == Cmp::Lt)
+        .any(|update| is_version_below(&os_version, update).unwrap_or(true))
 }
 
 fn convert_jamf_computer_details(
     jamf_computer_details: JamfComputer,
     mac_os_versions: Vec<String>,
+    extension_attributes: &ExtensionAttributesConfig,
+    staleness: StalenessConfig,
 ) -> Computer {
-    let name = jamf_computer_details.general.and_then(|g| Some(g.name));
-    let model = jamf_computer_details.hardware.and_then(|h| Some(h.model));
+    let extension_attributes = extension_attributes.values(&jamf_computer_details);
+    let check_in = check_in(
+        jamf_computer_details
+            .general
+            .as_ref()
+            .and_then(|g| g.last_contact_time.as_deref()),
+        jamf_computer_details
+            .general
+            .as_ref()
+            .and_then(|g| g.report_date.as_deref()),
+        staleness,
+        Utc::now(),
+    );
+    let name = jamf_computer_details.general.map(|g| g.name);
+    let model = jamf_computer_details.hardware.map(|h| h.model);
     let os = jamf_computer_details
         .operating_system
         .as_ref()
-        .and_then(|o| Some(o.name.clone()));
+        .map(|o| o.name.clone());
     let os_version = jamf_computer_details
         .operating_system
         .as_ref()
-        .and_then(|o| Some(o.version.clone()));
-    let os_is_updated = os_version.and_then(|v| Some(is_os_updated(v, mac_os_versions)));
+        .map(|o| o.version.clone());
+    let os_is_updated = os_version.map(|v| is_os_updated(v, mac_os_versions));
+    let security_checks = security_checks(jamf_computer_details.security.as_ref());
+    let disk_encryption = disk_encryption(
+        jamf_computer_details.disk_encryption,
+        jamf_computer_details.storage,
+        jamf_computer_details
+            .operating_system
+            .and_then(|o| o.file_vault2_status),
+    );
+    let disk_encrypted = disk_encryption
+        .as_ref()
+        .and_then(DiskEncryption::is_encrypted);
 
     Computer {
         name,
@@ -44,32 +177,194 @@ fn convert_jamf_computer_details(
         os,
         os_is_latest: os_is_updated,
         device_id: jamf_computer_details.id.clone(),
+        disk_encrypted,
+        disk_encryption,
+        compliance: computer_compliance(os_is_updated, disk_encrypted, &security_checks, &check_in),
+        check_in,
+        security_checks,
+        assigned_user: jamf_computer_details
+            .user_and_location
+            .and_then(assigned_user),
+        extension_attributes,
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Computer {
     device_id: Option<String>,
     name: Option<String>,
     model: Option<String>,
     os: Option<String>,
     os_is_latest: Option<bool>,
+    /// Whether FileVault protects the boot volume, unknown if Jamf didn't report it
+    disk_encrypted: Option<bool>,
+    disk_encryption: Option<DiskEncryption>,
+    /// Pass, fail or unknown for SIP, Gatekeeper, the firewall, XProtect and secure boot
+    security_checks: SecurityChecks,
+    check_in: CheckIn,
+    compliance: ComplianceVerdict,
+    /// Who the Mac is assigned to in Jamf, `None` if nobody is
+    assigned_user: Option<UserDetail>,
+    /// The extension attributes operators chose to expose, by their configured name
+    extension_attributes: BTreeMap<String, Option<ExtensionAttributeValue>>,
+}
+
+/// An iPhone, iPad or Apple TV
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct MobileDevice {
+    device_id: Option<String>,
+    name: Option<String>,
+    model: Option<String>,
+    os: Option<String>,
+    /// Compared against the iOS updates Jamf offers, unknown for Apple TVs and watches
+    os_is_latest: Option<bool>,
+    check_in: CheckIn,
+    compliance: ComplianceVerdict,
+    /// Who the device is assigned to in Jamf, `None` if nobody is
+    assigned_user: Option<UserDetail>,
+}
+
+/// Computers and mobile devices have separate ids in Jamf, `device_type` tells them apart
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(tag = "device_type", rename_all = "snake_case")]
+pub enum Device {
+    Computer(Computer),
+    MobileDevice(MobileDevice),
+}
+
+impl Device {
+    fn check_in(&self) -> &CheckIn {
+        match self {
+            Device::Computer(computer) => &computer.check_in,
+            Device::MobileDevice(mobile_device) => &mobile_device.check_in,
+        }
+    }
+
+    pub(crate) fn matches_extension_attributes(
+        &self,
+        filters: &[ExtensionAttributeFilter],
+    ) -> bool {
+        match self {
+            Device::Computer(computer) => filters
+                .iter()
+                .all(|f| f.matches(&computer.extension_attributes)),
+            Device::MobileDevice(_) => filters.is_empty(),
+        }
+    }
+}
+
+/// Jamf reports iPads as `iOS`, tell them apart by the model identifier, e.g. `iPad13,18`
+/// The inventory calls the platforms `iOS` and `tvOS`, the detail endpoint `ios` and `appleTv`
+pub(crate) fn mobile_os_name(
+    device_type: Option<&str>,
+    model_identifier: Option<&str>,
+) -> Option<String> {
+    let os = match device_type? {
+        "iOS" | "ios" if model_identifier.is_some_and(|m| m.starts_with("iPad")) => "iPadOS",
+        "iOS" | "ios" => "iOS",
+        "tvOS" | "appleTv" => "tvOS",
+        device_type => device_type,
+    };
+    Some(os.to_string())
+}
+
+/// iOS and iPadOS versions are in the iOS update list, other platforms can't be judged
+pub(crate) fn is_mobile_os_updated(
+    os: Option<&str>,
+    os_version: Option<String>,
+    ios_versions: Vec<String>,
+) -> Option<bool> {
+    match os {
+        Some("iOS" | "iPadOS") => os_version.map(|v| is_os_updated(v, ios_versions)),
+        _ => None,
+    }
+}
+
+fn convert_jamf_mobile_device(
+    jamf_mobile_device: JamfMobileDevice,
+    ios_versions: Vec<String>,
+    staleness: StalenessConfig,
+) -> MobileDevice {
+    let hardware = jamf_mobile_device.hardware.unwrap_or_default();
+    let general = jamf_mobile_device.general.unwrap_or_default();
+    let os = mobile_os_name(
+        jamf_mobile_device.device_type.as_deref(),
+        hardware.model_identifier.as_deref(),
+    );
+    let os_is_latest = is_mobile_os_updated(os.as_deref(), general.os_version, ios_versions);
+    // Mobile devices check in by sending inventory, there's no separate contact time
+    let check_in = check_in(
+        None,
+        general.last_inventory_update_date.as_deref(),
+        staleness,
+        Utc::now(),
+    );
+    MobileDevice {
+        device_id: jamf_mobile_device.mobile_device_id,
+        name: general.display_name,
+        model: hardware.model,
+        os_is_latest,
+        os,
+        compliance: compliance_verdict(&[
+            ("os_outdated", os_is_latest),
+            check_in.compliance_check(),
+        ]),
+        check_in,
+        assigned_user: jamf_mobile_device.user_and_location.and_then(assigned_user),
+    }
 }
 
 pub struct ComputerProvider {
     pub(crate) jamf_client: JamfClient,
+    pub(crate) extension_attributes: Arc<ExtensionAttributesConfig>,
+    pub(crate) staleness: StalenessConfig,
+}
+
+// Inventory sections needed to build a `Computer`
+fn computer_sections(
+    extension_attributes: &ExtensionAttributesConfig,
+) -> Vec<ComputerInventorySection> {
+    let mut sections = vec![
+        ComputerInventorySection::OperatingSystem,
+        ComputerInventorySection::General,
+        ComputerInventorySection::Hardware,
+        ComputerInventorySection::DiskEncryption,
+        ComputerInventorySection::Storage,
+        ComputerInventorySection::Security,
+        ComputerInventorySection::UserAndLocation,
+    ];
+    // Only downloaded when there's something to expose, they can be large
+    if !extension_attributes.is_empty() {
+        sections.push(ComputerInventorySection::ExtensionAttributes);
+    }
+    sections
+}
+
+// Keep the caller's filter and sort, but always request the sections a `Computer` is built from
+fn computer_query(
+    query: ComputerInventoryQuery,
+    extension_attributes: &ExtensionAttributesConfig,
+) -> ComputerInventoryQuery {
+    ComputerInventoryQuery {
+        sections: computer_sections(extension_attributes),
+        ..query
+    }
 }
 
 impl ComputerProvider {
-    pub async fn fetch_computers(&self) -> Result<DevicesOutput, JamfClientError> {
+    /// Jamf privileges the account needs for `fetch_computers`
+    pub const REQUIRED_PRIVILEGES: [&'static str; 2] =
+        ["Read Computers", "Read Managed Software Updates"];
+
+    /// Fetch the computers matching the filter of `query`, in its sort order
+    pub(crate) async fn fetch_computers(
+        &self,
+        query: ComputerInventoryQuery,
+    ) -> Result<DevicesOutput, JamfClientError> {
         // Get metadata for all computer devices
         let inventory = self
             .jamf_client
-            .get_computer_inventory(vec![
-                ComputerInventorySection::OperatingSystem,
-                ComputerInventorySection::General,
-                ComputerInventorySection::Hardware,
-            ])
+            .get_computer_inventory(computer_query(query, &self.extension_attributes))
             .await
             .inspect_err(|e| error!("Failed to fetch computers with error: {}", e))?;
 
@@ -81,44 +376,245 @@ impl ComputerProvider {
             .inspect_err(|e| error!("Failed to get OS versions with error {}", e))?;
 
         // Convert computer device metadata into DevicesOutput
-        let computers_output = DevicesOutput {
-            devices: inventory
+        let computers_output = DevicesOutput::new(
+            inventory
                 .results
                 .into_iter()
                 .map(|i| {
-                    convert_jamf_computer_details(i, os_versions.available_updates.mac_os.clone())
+                    Device::Computer(convert_jamf_computer_details(
+                        i,
+                        os_versions.available_updates.mac_os.clone(),
+                        &self.extension_attributes,
+                        self.staleness,
+                    ))
                 })
                 .collect(),
-        };
+        );
         Ok(computers_output)
     }
+
+    /// Fetch a single computer with everything Jamf knows about it, `NotFound` if there's no such computer
+    pub(crate) async fn fetch_computer(&self, id: &str) -> Result<ComputerDetail, JamfClientError> {
+        let computer = self
+            .jamf_client
+            .get_computer_detail(id)
+            .await
+            .inspect_err(|e| error!("Failed to fetch computer {} with error: {}", id, e))?;
+        let os_versions = self
+            .jamf_client
+            .get_os_managed_updates()
+            .await
+            .inspect_err(|e| error!("Failed to get OS versions with error {}", e))?;
+        Ok(convert_jamf_computer_detail(
+            computer,
+            os_versions.available_updates.mac_os,
+            &self.extension_attributes,
+            self.staleness,
+        ))
+    }
+
+    /// Every app installed on every Mac, fetched page by page
+    /// Jamf can't filter on applications, so searches run against this instead
+    pub(crate) async fn fetch_application_inventory(
+        &self,
+    ) -> Result<ApplicationInventory, JamfClientError> {
+        let inventory_query = ComputerInventoryQuery {
+            sections: vec![
+                ComputerInventorySection::General,
+                ComputerInventorySection::Applications,
+            ],
+            ..Default::default()
+        };
+        let mut pages = self.jamf_client.stream_computer_inventory(inventory_query);
+        let mut inventory = ApplicationInventory::default();
+        while let Some(page) = pages.next().await {
+            let page =
+                page.inspect_err(|e| error!("Failed to fetch applications with error: {}", e))?;
+            for computer in page {
+                inventory.add(computer);
+            }
+        }
+        Ok(inventory)
+    }
+
+    /// Same as `fetch_computers`, but yields computers page by page as Jamf returns them
+    /// Fails up front if the available OS updates can't be fetched, inventory errors come through the stream
+    pub(crate) async fn stream_computers(
+        &self,
+        query: ComputerInventoryQuery,
+    ) -> Result<BoxStream<'static, Result<Computer, JamfClientError>>, JamfClientError> {
+        let os_versions = self
+            .jamf_client
+            .get_os_managed_updates()
+            .await
+            .inspect_err(|e| error!("Failed to get OS versions with error {}", e))?;
+        let mac_os_versions = os_versions.available_updates.mac_os;
+        let extension_attributes = self.extension_attributes.clone();
+        let staleness = self.staleness;
+
+        Ok(self
+            .jamf_client
+            .stream_computer_inventory(computer_query(query, &self.extension_attributes))
+            .map_ok(move |page| {
+                let mac_os_versions = mac_os_versions.clone();
+                let extension_attributes = extension_attributes.clone();
+                stream::iter(page.into_iter().map(move |c| {
+                    Ok(convert_jamf_computer_details(
+                        c,
+                        mac_os_versions.clone(),
+                        &extension_attributes,
+                        staleness,
+                    ))
+                }))
+            })
+            .try_flatten()
+            .inspect_err(|e| error!("Failed to stream computers with error: {}", e))
+            .boxed())
+    }
+}
+
+pub struct MobileDeviceProvider {
+    pub(crate) jamf_client: JamfClient,
+    pub(crate) staleness: StalenessConfig,
+}
+
+// Inventory sections needed to build a `MobileDevice`
+fn mobile_device_query(query: MobileDeviceInventoryQuery) -> MobileDeviceInventoryQuery {
+    MobileDeviceInventoryQuery {
+        sections: vec![
+            MobileDeviceSection::General,
+            MobileDeviceSection::Hardware,
+            MobileDeviceSection::UserAndLocation,
+        ],
+        ..query
+    }
+}
+
+impl MobileDeviceProvider {
+    /// Jamf privileges the account needs for `fetch_mobile_devices`
+    pub const REQUIRED_PRIVILEGES: [&'static str; 2] =
+        ["Read Mobile Devices", "Read Managed Software Updates"];
+
+    /// Fetch the mobile devices matching the filter of `query`, in its sort order
+    pub(crate) async fn fetch_mobile_devices(
+        &self,
+        query: MobileDeviceInventoryQuery,
+    ) -> Result<Vec<MobileDevice>, JamfClientError> {
+        let inventory = self
+            .jamf_client
+            .get_mobile_device_inventory(mobile_device_query(query))
+            .await
+            .inspect_err(|e| error!("Failed to fetch mobile devices with error: {}", e))?;
+        let os_versions = self
+            .jamf_client
+            .get_os_managed_updates()
+            .await
+            .inspect_err(|e| error!("Failed to get OS versions with error {}", e))?;
+        Ok(inventory
+            .results
+            .into_iter()
+            .map(|d| {
+                convert_jamf_mobile_device(
+                    d,
+                    os_versions.available_updates.ios.clone(),
+                    self.staleness,
+                )
+            })
+            .collect())
+    }
+
+    /// Same as `fetch_mobile_devices`, but yields mobile devices page by page as Jamf returns them
+    pub(crate) async fn stream_mobile_devices(
+        &self,
+        query: MobileDeviceInventoryQuery,
+    ) -> Result<BoxStream<'static, Result<MobileDevice, JamfClientError>>, JamfClientError> {
+        let os_versions = self
+            .jamf_client
+            .get_os_managed_updates()
+            .await
+            .inspect_err(|e| error!("Failed to get OS versions with error {}", e))?;
+        let ios_versions = os_versions.available_updates.ios;
+        let staleness = self.staleness;
+
+        Ok(self
+            .jamf_client
+            .stream_mobile_device_inventory(mobile_device_query(query))
+            .map_ok(move |page| {
+                let ios_versions = ios_versions.clone();
+                stream::iter(page.into_iter().map(move |d| {
+                    Ok(convert_jamf_mobile_device(
+                        d,
+                        ios_versions.clone(),
+                        staleness,
+                    ))
+                }))
+            })
+            .try_flatten()
+            .inspect_err(|e| error!("Failed to stream mobile devices with error: {}", e))
+            .boxed())
+    }
+
+    /// Fetch a single mobile device, `NotFound` if there's no such device
+    pub(crate) async fn fetch_mobile_device(
+        &self,
+        id: &str,
+    ) -> Result<MobileDeviceDetail, JamfClientError> {
+        let mobile_device = self
+            .jamf_client
+            .get_mobile_device_detail(id)
+            .await
+            .inspect_err(|e| error!("Failed to fetch mobile device {} with error: {}", id, e))?;
+        let os_versions = self
+            .jamf_client
+            .get_os_managed_updates()
+            .await
+            .inspect_err(|e| error!("Failed to get OS versions with error {}", e))?;
+        Ok(convert_jamf_mobile_device_detail(
+            mobile_device,
+            os_versions.available_updates.ios,
+            self.staleness,
+        ))
+    }
 }
 
 #[cfg(test)]
-mod test {
+mod tests {
+    use crate::jamf::client::JamfClientError;
     use crate::jamf::{
+        applications::ApplicationQuery,
         client::{ComputerInventorySection, JamfClient, MockJamfClientTrait},
         models::{
             AvailableUpdates, JamfAvailableUpdates, JamfComputer, JamfComputerGeneral,
-            JamfComputerInventoryResponse, JamfComputerOperatingSystem,
+            JamfComputerInventoryResponse, JamfComputerOperatingSystem, JamfComputerSecurity,
+            JamfFileVault2Status, JamfGatekeeperStatus, JamfSecureBootLevel, JamfSipStatus,
         },
-        provider::{ComputerProvider, DevicesOutput},
+        posture::{disk_encryption, security_checks, CheckIn, ComplianceVerdict},
+        provider::{ComputerProvider, DevicesOutput, MobileDeviceProvider},
+        query::{ComputerField, ComputerInventoryQuery, Filter, MobileDeviceInventoryQuery},
     };
+    use futures::{stream, StreamExt};
+
+    use super::{computer_sections, Computer, Device};
 
-    use super::Computer;
+    static MOBILE_DEVICES_INVENTORY: &str = include_str!("testdata/mobile_devices_inventory.json");
+    static MOBILE_DEVICE_DETAIL: &str = include_str!("testdata/mobile_device_detail.json");
 
     #[tokio::test]
     async fn fetch_computers_empty() {
         let mut client_mock = MockJamfClientTrait::new();
         client_mock
             .expect_get_computer_inventory()
-            .return_once(|inventory_section| {
+            .return_once(|query| {
                 assert_eq!(
-                    inventory_section,
+                    query.sections,
                     vec![
                         ComputerInventorySection::OperatingSystem,
                         ComputerInventorySection::General,
                         ComputerInventorySection::Hardware,
+                        ComputerInventorySection::DiskEncryption,
+                        ComputerInventorySection::Storage,
+                        ComputerInventorySection::Security,
+                        ComputerInventorySection::UserAndLocation,
                     ]
                 );
                 Ok(JamfComputerInventoryResponse {
@@ -130,12 +626,16 @@ mod test {
             .expect_get_os_managed_updates()
             .return_once(|| Ok(test_available_updates()));
         let jamf_client = JamfClient::Mock(client_mock);
-        let computer_provider = ComputerProvider { jamf_client };
+        let computer_provider = ComputerProvider {
+            jamf_client,
+            extension_attributes: Default::default(),
+            staleness: Default::default(),
+        };
         let computers = computer_provider
-            .fetch_computers()
+            .fetch_computers(ComputerInventoryQuery::default())
             .await
             .expect("Should succeed");
-        assert_eq!(computers, DevicesOutput { devices: vec![] });
+        assert_eq!(computers, DevicesOutput::default());
     }
 
     #[tokio::test]
@@ -143,14 +643,12 @@ mod test {
         let mut client_mock = MockJamfClientTrait::new();
         client_mock
             .expect_get_computer_inventory()
-            .return_once(|inventory_section| {
+            .return_once(|query| {
+                assert_eq!(query.sections.len(), 7);
+                // The filter is passed on to Jamf, not applied by us
                 assert_eq!(
-                    inventory_section,
-                    vec![
-                        ComputerInventorySection::OperatingSystem,
-                        ComputerInventorySection::General,
-                        ComputerInventorySection::Hardware,
-                    ]
+                    query.filter,
+                    Some(Filter::eq(ComputerField::GeneralName, "mac-*"))
                 );
                 Ok(test_inventory_response())
             });
@@ -158,19 +656,217 @@ mod test {
             .expect_get_os_managed_updates()
             .return_once(|| Ok(test_available_updates()));
         let jamf_client = JamfClient::Mock(client_mock);
-        let computer_provider = ComputerProvider { jamf_client };
+        let computer_provider = ComputerProvider {
+            jamf_client,
+            extension_attributes: Default::default(),
+            staleness: Default::default(),
+        };
         let computers = computer_provider
-            .fetch_computers()
+            .fetch_computers(
+                ComputerInventoryQuery::default()
+                    .filter(Filter::eq(ComputerField::GeneralName, "mac-*")),
+            )
             .await
             .expect("Should succeed");
         assert_eq!(
             computers,
-            DevicesOutput {
-                devices: vec![test_computer_output()]
-            }
+            DevicesOutput::new(vec![Device::Computer(test_computer_output())])
+        );
+        let computers = serde_json::to_value(computers).unwrap();
+        assert_eq!(computers["summary"]["computers"], 1);
+        assert_eq!(computers["summary"]["disk_encryption"]["encrypted"], 1);
+        assert_eq!(computers["devices"][0]["security_checks"]["sip"], "pass");
+    }
+
+    #[tokio::test]
+    async fn fetch_computer_detail() {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock
+            .expect_get_computer_detail()
+            .withf(|id| id == "test_id")
+            .return_once(|_| Ok(test_inventory_response().results.remove(0)));
+        client_mock
+            .expect_get_os_managed_updates()
+            .return_once(|| Ok(test_available_updates()));
+        let computer_provider = ComputerProvider {
+            jamf_client: JamfClient::Mock(client_mock),
+            extension_attributes: Default::default(),
+            staleness: Default::default(),
+        };
+        let computer = computer_provider
+            .fetch_computer("test_id")
+            .await
+            .expect("Should succeed");
+        let computer = serde_json::to_value(computer).unwrap();
+        assert_eq!(computer["device_id"], "test_id");
+        assert_eq!(computer["compliance"]["compliant"], true);
+    }
+
+    #[tokio::test]
+    async fn fetch_computer_not_found() {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock.expect_get_computer_detail().return_once(|_| {
+            Err(JamfClientError::NotFound {
+                path: "/api/v1/computers-inventory-detail/404".to_string(),
+            })
+        });
+        let computer_provider = ComputerProvider {
+            jamf_client: JamfClient::Mock(client_mock),
+            extension_attributes: Default::default(),
+            staleness: Default::default(),
+        };
+        let result = computer_provider.fetch_computer("404").await;
+        assert!(matches!(result, Err(JamfClientError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn stream_computers_page_by_page() {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock
+            .expect_stream_computer_inventory()
+            .return_once(|query| {
+                assert_eq!(query.sections, computer_sections(&Default::default()));
+                let pages = vec![
+                    Ok(test_inventory_response().results),
+                    Ok(test_inventory_response().results),
+                    Err(JamfClientError::NotFound {
+                        path: "/api/v1/computers-inventory".to_string(),
+                    }),
+                ];
+                stream::iter(pages).boxed()
+            });
+        client_mock
+            .expect_get_os_managed_updates()
+            .return_once(|| Ok(test_available_updates()));
+        let computer_provider = ComputerProvider {
+            jamf_client: JamfClient::Mock(client_mock),
+            extension_attributes: Default::default(),
+            staleness: Default::default(),
+        };
+        let computers = computer_provider
+            .stream_computers(ComputerInventoryQuery::default())
+            .await
+            .expect("Should start streaming")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(computers.len(), 3);
+        assert_eq!(computers[0].as_ref().unwrap(), &test_computer_output());
+        assert_eq!(computers[1].as_ref().unwrap(), &test_computer_output());
+        assert!(matches!(
+            computers[2],
+            Err(JamfClientError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_mobile_devices_against_ios_updates() {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock
+            .expect_get_mobile_device_inventory()
+            .return_once(|query| {
+                assert_eq!(query.sections.len(), 3);
+                Ok(serde_json::from_str(MOBILE_DEVICES_INVENTORY).unwrap())
+            });
+        client_mock
+            .expect_get_os_managed_updates()
+            .return_once(|| Ok(test_available_updates()));
+        let mobile_device_provider = MobileDeviceProvider {
+            jamf_client: JamfClient::Mock(client_mock),
+            staleness: Default::default(),
+        };
+        let mobile_devices = mobile_device_provider
+            .fetch_mobile_devices(MobileDeviceInventoryQuery::default())
+            .await
+            .expect("Should succeed");
+        let mobile_devices =
+            serde_json::to_value(DevicesOutput::default().with_mobile_devices(mobile_devices))
+                .unwrap();
+        let devices = mobile_devices["devices"].as_array().unwrap();
+
+        assert_eq!(devices[0]["device_type"], "mobile_device");
+        assert_eq!(devices[0]["os"], "iOS");
+        assert_eq!(devices[0]["os_is_latest"], true);
+        assert_eq!(devices[0]["assigned_user"]["email"], "peter@example.com");
+        assert_eq!(devices[1]["os"], "iPadOS");
+        assert_eq!(devices[1]["os_is_latest"], false);
+        assert_eq!(devices[1]["compliance"]["failures"][0], "os_outdated");
+        // Jamf reports a user without any details for unassigned devices
+        assert!(devices[1]["assigned_user"].is_null());
+        // Jamf doesn't list tvOS updates
+        assert_eq!(devices[2]["os"], "tvOS");
+        assert!(devices[2]["os_is_latest"].is_null());
+    }
+
+    #[tokio::test]
+    async fn fetch_mobile_device_detail() {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock
+            .expect_get_mobile_device_detail()
+            .withf(|id| id == "1")
+            .return_once(|_| Ok(serde_json::from_str(MOBILE_DEVICE_DETAIL).unwrap()));
+        client_mock
+            .expect_get_os_managed_updates()
+            .return_once(|| Ok(test_available_updates()));
+        let mobile_device_provider = MobileDeviceProvider {
+            jamf_client: JamfClient::Mock(client_mock),
+            staleness: Default::default(),
+        };
+        let mobile_device = mobile_device_provider
+            .fetch_mobile_device("1")
+            .await
+            .expect("Should succeed");
+        let mobile_device = serde_json::to_value(mobile_device).unwrap();
+        assert_eq!(mobile_device["operating_system"]["name"], "iOS");
+        assert_eq!(mobile_device["security"]["passcode_compliant"], true);
+        assert_eq!(mobile_device["user"]["email"], "peter@example.com");
+        // Nested, so it isn't mistaken for the list level flag of a list served from cache
+        assert_eq!(mobile_device["check_in"]["stale"], true);
+        assert!(mobile_device.get("stale").is_none());
+        // Its OS is up to date, but it last sent inventory in 2024 so the verdict can't be trusted
+        assert_eq!(mobile_device["compliance"]["compliant"], false);
+        assert_eq!(
+            mobile_device["compliance"]["failures"],
+            serde_json::json!(["stale_check_in"])
         );
     }
 
+    #[tokio::test]
+    async fn application_inventory_across_pages() {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock
+            .expect_stream_computer_inventory()
+            .return_once(|query| {
+                assert_eq!(
+                    query.sections,
+                    vec![
+                        ComputerInventorySection::General,
+                        ComputerInventorySection::Applications,
+                    ]
+                );
+                let inventory: JamfComputerInventoryResponse =
+                    serde_json::from_str(include_str!("testdata/computers_inventory.json"))
+                        .unwrap();
+                let pages = inventory.results.into_iter().map(|c| Ok(vec![c]));
+                stream::iter(pages.collect::<Vec<_>>()).boxed()
+            });
+        let computer_provider = ComputerProvider {
+            jamf_client: JamfClient::Mock(client_mock),
+            extension_attributes: Default::default(),
+            staleness: Default::default(),
+        };
+        let inventory = computer_provider
+            .fetch_application_inventory()
+            .await
+            .expect("Should succeed");
+        let applications = serde_json::to_value(inventory.search(&ApplicationQuery {
+            bundle_id: "com.google.Chrome".to_string(),
+            below_version: Some("124.0.6367.200".to_string()),
+        }))
+        .unwrap();
+        assert_eq!(applications["computers_scanned"], 2);
+        assert_eq!(applications["applications"][0]["version"], "124.0.6367.119");
+    }
+
     // TODO: If I had more time, I'd write tests for more cases, mock errors etc... :)
 
     fn test_computer_output() -> Computer {
@@ -180,6 +876,18 @@ mod test {
             model: None,
             os: Some("MacOS".to_string()),
             os_is_latest: Some(true),
+            disk_encrypted: Some(true),
+            disk_encryption: disk_encryption(None, None, Some(JamfFileVault2Status::BootEncrypted)),
+            security_checks: security_checks(
+                test_inventory_response().results[0].security.as_ref(),
+            ),
+            compliance: ComplianceVerdict {
+                compliant: Some(true),
+                failures: vec![],
+            },
+            check_in: CheckIn::default(),
+            assigned_user: None,
+            extension_attributes: Default::default(),
         }
     }
 
@@ -187,19 +895,28 @@ mod test {
         JamfComputerInventoryResponse {
             total_count: 1,
             results: vec![JamfComputer {
-                hardware: None,
-                software: None,
                 operating_system: Some(JamfComputerOperatingSystem {
                     name: "MacOS".to_string(),
                     version: "14.0.0".to_string(),
                     build: "whatever".to_string(),
-                    software_updates: None,
+                    file_vault2_status: Some(JamfFileVault2Status::BootEncrypted),
+                    ..Default::default()
                 }),
                 general: Some(JamfComputerGeneral {
                     name: "test_name".to_string(),
+                    ..Default::default()
+                }),
+                security: Some(JamfComputerSecurity {
+                    sip_status: Some(JamfSipStatus::Enabled),
+                    gatekeeper_status: Some(JamfGatekeeperStatus::AppStore),
+                    firewall_enabled: Some(true),
+                    xprotect_version: Some("2193".to_string()),
+                    secure_boot_level: Some(JamfSecureBootLevel::FullSecurity),
+                    ..Default::default()
                 }),
                 id: Some("test_id".to_string()),
                 udid: Some("udid_test".to_string()),
+                ..Default::default()
             }],
         }
     }
@@ -208,7 +925,7 @@ mod test {
         JamfAvailableUpdates {
             available_updates: AvailableUpdates {
                 mac_os: vec!["14.0.0".to_string()],
-                ios: vec![],
+                ios: vec!["17.4.1".to_string()],
             },
         }
     }
@@ -0,0 +1,466 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::client::{ComputerInventorySection, MobileDeviceSection};
+
+/// Computer inventory fields Jamf can filter and sort on
+/// note: This enum is not exhaustive
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ComputerField {
+    Id,
+    Udid,
+    GeneralName,
+    GeneralReportDate,
+    GeneralLastContactTime,
+    HardwareSerialNumber,
+    HardwareModel,
+    OperatingSystemName,
+    OperatingSystemVersion,
+    UserAndLocationEmail,
+}
+
+impl ComputerField {
+    fn path(&self) -> &'static str {
+        match self {
+            ComputerField::Id => "id",
+            ComputerField::Udid => "udid",
+            ComputerField::GeneralName => "general.name",
+            ComputerField::GeneralReportDate => "general.reportDate",
+            ComputerField::GeneralLastContactTime => "general.lastContactTime",
+            ComputerField::HardwareSerialNumber => "hardware.serialNumber",
+            ComputerField::HardwareModel => "hardware.model",
+            ComputerField::OperatingSystemName => "operatingSystem.name",
+            ComputerField::OperatingSystemVersion => "operatingSystem.version",
+            ComputerField::UserAndLocationEmail => "userAndLocation.email",
+        }
+    }
+}
+
+impl fmt::Display for ComputerField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path())
+    }
+}
+
+impl FromStr for ComputerField {
+    type Err = String;
+
+    /// Parse the field by its Jamf name, e.g. `general.reportDate`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            ComputerField::Id,
+            ComputerField::Udid,
+            ComputerField::GeneralName,
+            ComputerField::GeneralReportDate,
+            ComputerField::GeneralLastContactTime,
+            ComputerField::HardwareSerialNumber,
+            ComputerField::HardwareModel,
+            ComputerField::OperatingSystemName,
+            ComputerField::OperatingSystemVersion,
+            ComputerField::UserAndLocationEmail,
+        ]
+        .into_iter()
+        .find(|field| field.path() == s)
+        .ok_or_else(|| format!("Unknown computer inventory field {:?}", s))
+    }
+}
+
+/// Mobile device inventory fields Jamf can filter and sort on
+/// note: This enum is not exhaustive
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum MobileDeviceField {
+    Id,
+    Udid,
+    DisplayName,
+    LastInventoryUpdateDate,
+    SerialNumber,
+    Model,
+    OsVersion,
+    UserAndLocationEmail,
+}
+
+impl MobileDeviceField {
+    fn path(&self) -> &'static str {
+        match self {
+            MobileDeviceField::Id => "mobileDeviceId",
+            MobileDeviceField::Udid => "general.udid",
+            MobileDeviceField::DisplayName => "general.displayName",
+            MobileDeviceField::LastInventoryUpdateDate => "general.lastInventoryUpdateDate",
+            MobileDeviceField::SerialNumber => "hardware.serialNumber",
+            MobileDeviceField::Model => "hardware.model",
+            MobileDeviceField::OsVersion => "general.osVersion",
+            MobileDeviceField::UserAndLocationEmail => "userAndLocation.emailAddress",
+        }
+    }
+
+    /// The mobile device field holding the same thing as a computer field, if there is one
+    pub(crate) fn matching(field: ComputerField) -> Option<Self> {
+        match field {
+            ComputerField::Id => Some(MobileDeviceField::Id),
+            ComputerField::Udid => Some(MobileDeviceField::Udid),
+            ComputerField::GeneralName => Some(MobileDeviceField::DisplayName),
+            ComputerField::GeneralReportDate => Some(MobileDeviceField::LastInventoryUpdateDate),
+            ComputerField::HardwareSerialNumber => Some(MobileDeviceField::SerialNumber),
+            ComputerField::HardwareModel => Some(MobileDeviceField::Model),
+            ComputerField::OperatingSystemVersion => Some(MobileDeviceField::OsVersion),
+            ComputerField::UserAndLocationEmail => Some(MobileDeviceField::UserAndLocationEmail),
+            ComputerField::GeneralLastContactTime | ComputerField::OperatingSystemName => None,
+        }
+    }
+}
+
+impl fmt::Display for MobileDeviceField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path())
+    }
+}
+
+/// A value compared against a field, rendered the way Jamf expects it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct FilterValue(String);
+
+impl From<&str> for FilterValue {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<DateTime<Utc>> for FilterValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self(value.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+}
+
+// Characters with a meaning in RSQL, values containing them have to be quoted
+static RESERVED: &[char] = &['"', '\'', '(', ')', ';', ',', '=', '!', '~', '<', '>'];
+
+impl fmt::Display for FilterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.0.is_empty() && !self.0.contains(RESERVED) && !self.0.contains(char::is_whitespace)
+        {
+            return write!(f, "{}", self.0);
+        }
+        write!(
+            f,
+            "\"{}\"",
+            self.0.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    }
+}
+
+/// RSQL filter for Jamf's inventory endpoints, e.g. `general.name==Peter*;general.reportDate>2024-01-01T00:00:00Z`
+/// `eq` accepts `*` wildcards
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Filter<F = ComputerField> {
+    Eq(F, FilterValue),
+    Gt(F, FilterValue),
+    Lt(F, FilterValue),
+    In(F, Vec<FilterValue>),
+    And(Vec<Filter<F>>),
+}
+
+impl<F> Filter<F> {
+    pub(crate) fn eq(field: F, value: impl Into<FilterValue>) -> Self {
+        Filter::Eq(field, value.into())
+    }
+
+    pub(crate) fn gt(field: F, value: impl Into<FilterValue>) -> Self {
+        Filter::Gt(field, value.into())
+    }
+
+    pub(crate) fn lt(field: F, value: impl Into<FilterValue>) -> Self {
+        Filter::Lt(field, value.into())
+    }
+
+    pub(crate) fn is_in<V: Into<FilterValue>>(
+        field: F,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Filter::In(field, values.into_iter().map(Into::into).collect())
+    }
+
+    /// Both filters have to match
+    pub(crate) fn and(self, other: Filter<F>) -> Self {
+        let mut filters = match self {
+            Filter::And(filters) => filters,
+            filter => vec![filter],
+        };
+        match other {
+            Filter::And(other) => filters.extend(other),
+            other => filters.push(other),
+        }
+        Filter::And(filters)
+    }
+
+    /// The same filter on other fields, `None` if a field has no counterpart
+    pub(crate) fn map_fields<G>(self, map: &impl Fn(F) -> Option<G>) -> Option<Filter<G>> {
+        Some(match self {
+            Filter::Eq(field, value) => Filter::Eq(map(field)?, value),
+            Filter::Gt(field, value) => Filter::Gt(map(field)?, value),
+            Filter::Lt(field, value) => Filter::Lt(map(field)?, value),
+            Filter::In(field, values) => Filter::In(map(field)?, values),
+            Filter::And(filters) => Filter::And(
+                filters
+                    .into_iter()
+                    .map(|filter| filter.map_fields(map))
+                    .collect::<Option<_>>()?,
+            ),
+        })
+    }
+}
+
+impl<F: fmt::Display> fmt::Display for Filter<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Eq(field, value) => write!(f, "{}=={}", field, value),
+            Filter::Gt(field, value) => write!(f, "{}>{}", field, value),
+            Filter::Lt(field, value) => write!(f, "{}<{}", field, value),
+            Filter::In(field, values) => write!(
+                f,
+                "{}=in=({})",
+                field,
+                values
+                    .iter()
+                    .map(FilterValue::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Filter::And(filters) => write!(
+                f,
+                "{}",
+                filters
+                    .iter()
+                    .map(Filter::to_string)
+                    .collect::<Vec<_>>()
+                    .join(";")
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Parse a Jamf style sort, e.g. `general.reportDate:desc,id:asc`, the direction defaults to ascending
+pub(crate) fn parse_sort(s: &str) -> Result<Vec<(ComputerField, SortDirection)>, String> {
+    s.split(',')
+        .map(|criterion| {
+            let (field, direction) = criterion.split_once(':').unwrap_or((criterion, "asc"));
+            let direction = match direction {
+                "asc" => SortDirection::Asc,
+                "desc" => SortDirection::Desc,
+                direction => return Err(format!("Unknown sort direction {:?}", direction)),
+            };
+            Ok((field.parse()?, direction))
+        })
+        .collect()
+}
+
+/// Which sections, devices and order to request from one of Jamf's inventory endpoints
+/// Filtering and sorting happen in Jamf, so we only download the devices we need
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct InventoryQuery<S, F> {
+    pub(crate) sections: Vec<S>,
+    pub(crate) filter: Option<Filter<F>>,
+    pub(crate) sort: Vec<(F, SortDirection)>,
+}
+
+pub(crate) type ComputerInventoryQuery = InventoryQuery<ComputerInventorySection, ComputerField>;
+
+pub(crate) type MobileDeviceInventoryQuery = InventoryQuery<MobileDeviceSection, MobileDeviceField>;
+
+// Derived `Default` would require `S: Default` and `F: Default`
+impl<S, F> Default for InventoryQuery<S, F> {
+    fn default() -> Self {
+        Self {
+            sections: vec![],
+            filter: None,
+            sort: vec![],
+        }
+    }
+}
+
+impl<S: fmt::Display, F: fmt::Display> InventoryQuery<S, F> {
+    /// Only return devices matching `filter`, calling this again narrows the filter further
+    pub(crate) fn filter(mut self, filter: Filter<F>) -> Self {
+        self.filter = Some(match self.filter {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    /// Sort by `field`, later calls break ties of earlier ones
+    pub(crate) fn sort_by(mut self, field: F, direction: SortDirection) -> Self {
+        self.sort.push((field, direction));
+        self
+    }
+
+    /// Query parameters for Jamf, without paging
+    pub(crate) fn params(&self) -> Vec<(String, String)> {
+        let mut params = self
+            .sections
+            .iter()
+            .map(|s| ("section".to_string(), s.to_string()))
+            .collect::<Vec<(String, String)>>();
+        if let Some(filter) = &self.filter {
+            params.push(("filter".to_string(), filter.to_string()));
+        }
+        if !self.sort.is_empty() {
+            let sort = self
+                .sort
+                .iter()
+                .map(|(field, direction)| match direction {
+                    SortDirection::Asc => format!("{}:asc", field),
+                    SortDirection::Desc => format!("{}:desc", field),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            params.push(("sort".to_string(), sort));
+        }
+        params
+    }
+}
+
+impl MobileDeviceInventoryQuery {
+    /// The same filter and sort for mobile devices, `None` if the filter uses a field mobile
+    /// devices don't have, so no mobile device can match
+    /// Sorting on such a field is dropped
+    pub(crate) fn matching(query: &ComputerInventoryQuery) -> Option<Self> {
+        Some(Self {
+            sections: vec![],
+            filter: match &query.filter {
+                Some(filter) => Some(filter.clone().map_fields(&MobileDeviceField::matching)?),
+                None => None,
+            },
+            sort: query
+                .sort
+                .iter()
+                .filter_map(|(field, direction)| {
+                    MobileDeviceField::matching(*field).map(|field| (field, *direction))
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::jamf::client::ComputerInventorySection;
+
+    use super::{
+        parse_sort, ComputerField, ComputerInventoryQuery, Filter, MobileDeviceInventoryQuery,
+        SortDirection,
+    };
+
+    #[test]
+    fn filters_rendered_as_rsql() {
+        let since = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let filter = Filter::eq(ComputerField::GeneralName, "Peter*")
+            .and(Filter::gt(ComputerField::GeneralReportDate, since))
+            .and(Filter::eq(
+                ComputerField::HardwareSerialNumber,
+                "C02XL0GYJGH5",
+            ))
+            .and(Filter::is_in(
+                ComputerField::OperatingSystemVersion,
+                ["14.4", "14.5"],
+            ));
+        assert_eq!(
+            filter.to_string(),
+            "general.name==Peter*;general.reportDate>2024-01-01T00:00:00Z;\
+             hardware.serialNumber==C02XL0GYJGH5;operatingSystem.version=in=(14.4,14.5)"
+        );
+    }
+
+    #[test]
+    fn reserved_characters_quoted() {
+        assert_eq!(
+            Filter::eq(ComputerField::GeneralName, "peter’s MacBook Air").to_string(),
+            "general.name==\"peter’s MacBook Air\""
+        );
+        assert_eq!(
+            Filter::eq(ComputerField::GeneralName, r#"a "b" (c)"#).to_string(),
+            r#"general.name=="a \"b\" (c)""#
+        );
+        assert_eq!(
+            Filter::eq(ComputerField::GeneralName, "").to_string(),
+            "general.name==\"\""
+        );
+    }
+
+    #[test]
+    fn query_params() {
+        let query = ComputerInventoryQuery {
+            sections: vec![ComputerInventorySection::General],
+            ..Default::default()
+        }
+        .filter(Filter::eq(ComputerField::GeneralName, "mac-1"))
+        .filter(Filter::lt(
+            ComputerField::GeneralLastContactTime,
+            Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap(),
+        ))
+        .sort_by(ComputerField::GeneralReportDate, SortDirection::Desc)
+        .sort_by(ComputerField::Id, SortDirection::Asc);
+        let params = query.params();
+        let param = |name: &str| {
+            params
+                .iter()
+                .filter(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(param("section"), ["GENERAL"]);
+        assert_eq!(
+            param("filter"),
+            ["general.name==mac-1;general.lastContactTime<2024-06-01T12:00:00Z"]
+        );
+        assert_eq!(param("sort"), ["general.reportDate:desc,id:asc"]);
+    }
+
+    #[test]
+    fn sort_parsed() {
+        assert_eq!(
+            parse_sort("general.reportDate:desc,id").unwrap(),
+            [
+                (ComputerField::GeneralReportDate, SortDirection::Desc),
+                (ComputerField::Id, SortDirection::Asc)
+            ]
+        );
+        assert!(parse_sort("general.password:asc").is_err());
+        assert!(parse_sort("id:sideways").is_err());
+    }
+
+    #[test]
+    fn mobile_device_query_matching() {
+        let query = ComputerInventoryQuery::default()
+            .filter(Filter::eq(ComputerField::GeneralName, "Peter*"))
+            .sort_by(ComputerField::OperatingSystemName, SortDirection::Asc)
+            .sort_by(ComputerField::HardwareSerialNumber, SortDirection::Desc);
+        let params = MobileDeviceInventoryQuery::matching(&query)
+            .unwrap()
+            .params();
+        assert!(params.contains(&(
+            "filter".to_string(),
+            "general.displayName==Peter*".to_string()
+        )));
+        // Mobile devices have no OS name to sort by
+        assert!(params.contains(&("sort".to_string(), "hardware.serialNumber:desc".to_string())));
+
+        // No mobile device can match a filter on a computer only field
+        let query = query.filter(Filter::eq(ComputerField::OperatingSystemName, "macOS"));
+        assert!(MobileDeviceInventoryQuery::matching(&query).is_none());
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, sync::Arc, sync::Mutex, time::Duration};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{sleep, Instant},
+};
+use tracing::debug;
+
+use super::config::env_or;
+
+/// How hard we let ourselves hit a single Jamf instance
+/// Jamf Cloud throttles clients that send bursts of requests, so we stay below that on our side
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Requests per second once the burst is used up
+    pub requests_per_second: u32,
+    /// Requests that can be sent back to back after the instance was idle
+    pub burst: u32,
+    /// Requests in flight at the same time, across every client talking to the instance
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 10,
+            burst: 10,
+            max_concurrent_requests: 5,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn new(requests_per_second: u32, burst: u32, max_concurrent_requests: usize) -> Self {
+        Self {
+            requests_per_second: requests_per_second.max(1),
+            burst: burst.max(1),
+            max_concurrent_requests: max_concurrent_requests.max(1),
+        }
+    }
+
+    /// Read the config from `JAMF_RATE_LIMIT_RPS`, `JAMF_RATE_LIMIT_BURST` and
+    /// `JAMF_MAX_CONCURRENT_REQUESTS`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self::new(
+            env_or("JAMF_RATE_LIMIT_RPS", default.requests_per_second),
+            env_or("JAMF_RATE_LIMIT_BURST", default.burst),
+            env_or(
+                "JAMF_MAX_CONCURRENT_REQUESTS",
+                default.max_concurrent_requests,
+            ),
+        )
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket plus a concurrency cap for one Jamf instance
+pub(crate) struct RateLimiter {
+    config: RateLimitConfig,
+    bucket: Mutex<TokenBucket>,
+    in_flight: Arc<Semaphore>,
+}
+
+/// Held while a request is in flight, dropping it lets the next request through
+pub(crate) struct RateLimitPermit {
+    _in_flight: OwnedSemaphorePermit,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket {
+                tokens: config.burst as f64,
+                refilled_at: Instant::now(),
+            }),
+            in_flight: Arc::new(Semaphore::new(config.max_concurrent_requests)),
+            config,
+        }
+    }
+
+    /// Wait for a free request slot and a token, keep the permit until the response body is read
+    pub(crate) async fn acquire(&self) -> RateLimitPermit {
+        let in_flight = self
+            .in_flight
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Rate limiter semaphore is never closed");
+        self.throttle().await;
+        RateLimitPermit {
+            _in_flight: in_flight,
+        }
+    }
+
+    // Wait for a token without taking a request slot
+    async fn throttle(&self) {
+        while let Some(wait) = self.take_token() {
+            debug!("Rate limiting Jamf request for {:?}", wait);
+            sleep(wait).await;
+        }
+    }
+
+    // Take a token if there is one, otherwise return how long until the next one
+    // NOTE: This is a std Mutex, it is never held across an await
+    fn take_token(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().expect("Rate limiter poisoned");
+        let now = Instant::now();
+        let rate = self.config.requests_per_second as f64;
+        let refilled = bucket.tokens + (now - bucket.refilled_at).as_secs_f64() * rate;
+        bucket.tokens = refilled.min(self.config.burst as f64);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+}
+
+/// One rate limiter per Jamf instance, shared by every client and tenant talking to it
+/// Jamf throttles per instance, so two connections to the same instance share one budget
+#[derive(Default)]
+pub(crate) struct RateLimiters {
+    config: RateLimitConfig,
+    limiters: Mutex<HashMap<String, Arc<RateLimiter>>>,
+}
+
+// `https://Acme.jamfcloud.com/` and `https://acme.jamfcloud.com` are the same instance
+fn instance_key(jamf_url: &str) -> String {
+    reqwest::Url::parse(jamf_url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| jamf_url.trim_end_matches('/').to_lowercase())
+}
+
+impl RateLimiters {
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            limiters: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn for_instance(&self, jamf_url: &str) -> Arc<RateLimiter> {
+        self.limiters
+            .lock()
+            .expect("Rate limiter registry poisoned")
+            .entry(instance_key(jamf_url))
+            .or_insert_with(|| Arc::new(RateLimiter::new(self.config.clone())))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures::future::join_all;
+    use tokio::time::Instant;
+
+    use super::{RateLimitConfig, RateLimiter, RateLimiters};
+
+    #[tokio::test]
+    async fn requests_beyond_burst_wait_for_tokens() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(50, 2, 10));
+        let started = Instant::now();
+        for _ in 0..2 {
+            limiter.acquire().await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(15));
+        // Two more tokens at 50 per second take at least 40ms
+        for _ in 0..2 {
+            limiter.acquire().await;
+        }
+        assert!(started.elapsed() >= Duration::from_millis(35));
+    }
+
+    #[tokio::test]
+    async fn concurrency_capped() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(1000, 1000, 2));
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        join_all((0..6).map(|_| async {
+            let _permit = limiter.acquire().await;
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+        }))
+        .await;
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn limiters_shared_per_instance() {
+        let limiters = RateLimiters::default();
+        let first = limiters.for_instance("https://acme.jamfcloud.com");
+        assert!(Arc::ptr_eq(
+            &first,
+            &limiters.for_instance("https://ACME.jamfcloud.com/")
+        ));
+        assert!(!Arc::ptr_eq(
+            &first,
+            &limiters.for_instance("https://other.jamfcloud.com")
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use reqwest::StatusCode;
+use tokio::time::sleep;
+use tracing::{warn, Span};
+
+use super::{client::JamfClientError, config::env_or};
+
+/// How failed requests to Jamf are retried
+/// Only used for idempotent GETs, a retried POST could e.g. create a second token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one, 1 disables retrying
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every retry after that
+    pub base_delay: Duration,
+    /// Upper bound for backoff, we also give up if Jamf asks us to wait longer than this
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Read the policy from `JAMF_RETRY_MAX_ATTEMPTS`, `JAMF_RETRY_BASE_DELAY_MS` and
+    /// `JAMF_RETRY_MAX_DELAY_MS`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_attempts: env_or("JAMF_RETRY_MAX_ATTEMPTS", default.max_attempts).max(1),
+            base_delay: Duration::from_millis(env_or(
+                "JAMF_RETRY_BASE_DELAY_MS",
+                default.base_delay.as_millis() as u64,
+            )),
+            max_delay: Duration::from_millis(env_or(
+                "JAMF_RETRY_MAX_DELAY_MS",
+                default.max_delay.as_millis() as u64,
+            )),
+        }
+    }
+
+    /// Backoff before retry number `retry` (starting at 1), with full jitter so clients that failed
+    /// together don't all come back at the same time
+    fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// How long to wait before retrying after `error`, `None` if it shouldn't be retried
+    fn delay(&self, error: &JamfClientError, retry: u32) -> Option<Duration> {
+        match error {
+            JamfClientError::RateLimited { retry_after, .. }
+            | JamfClientError::ServerError {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                retry_after,
+                ..
+            } => match retry_after {
+                Some(retry_after) if *retry_after > self.max_delay => None,
+                Some(retry_after) => Some(*retry_after),
+                None => Some(self.backoff(retry)),
+            },
+            // NOTE: 501 Not Implemented won't change by asking again
+            JamfClientError::ServerError { status, .. }
+                if *status != StatusCode::NOT_IMPLEMENTED =>
+            {
+                Some(self.backoff(retry))
+            }
+            JamfClientError::ReqwestError(e) if e.is_connect() || e.is_timeout() => {
+                Some(self.backoff(retry))
+            }
+            _ => None,
+        }
+    }
+
+    /// Run `request` until it succeeds, fails with an error that isn't transient or runs out of attempts
+    /// The number of retries is recorded in the `retries` field of the current span
+    pub(crate) async fn retry<T, F, Fut>(&self, mut request: F) -> Result<T, JamfClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, JamfClientError>>,
+    {
+        let mut retries = 0;
+        loop {
+            let error = match request().await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            let delay = match self.delay(&error, retries + 1) {
+                Some(delay) if retries + 1 < self.max_attempts => delay,
+                _ => return Err(error),
+            };
+            retries += 1;
+            Span::current().record("retries", retries);
+            warn!(
+                "Jamf request failed, retry {} of {} in {:?}: {}",
+                retries,
+                self.max_attempts - 1,
+                delay,
+                error
+            );
+            sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use reqwest::StatusCode;
+
+    use crate::jamf::client::JamfClientError;
+
+    use super::RetryPolicy;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(50),
+        }
+    }
+
+    fn server_error(status: StatusCode, retry_after: Option<Duration>) -> JamfClientError {
+        JamfClientError::ServerError {
+            path: "/api/v1/computers-inventory".to_string(),
+            status,
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn backoff_bounded() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+        };
+        for retry in 1..10 {
+            let ceiling = Duration::from_millis(100 * 2u64.pow(retry - 1)).min(policy.max_delay);
+            assert!(policy.backoff(retry) <= ceiling);
+        }
+    }
+
+    #[test]
+    fn only_transient_errors_retried() {
+        let policy = policy(3);
+        assert!(policy
+            .delay(&server_error(StatusCode::BAD_GATEWAY, None), 1)
+            .is_some());
+        assert!(policy
+            .delay(&server_error(StatusCode::NOT_IMPLEMENTED, None), 1)
+            .is_none());
+        let not_found = JamfClientError::NotFound {
+            path: "/api/v1/computers-inventory".to_string(),
+        };
+        assert!(policy.delay(&not_found, 1).is_none());
+    }
+
+    #[test]
+    fn retry_after_honored() {
+        let policy = policy(3);
+        let rate_limited = JamfClientError::RateLimited {
+            path: "/api/v1/computers-inventory".to_string(),
+            retry_after: Some(Duration::from_millis(20)),
+        };
+        assert_eq!(
+            policy.delay(&rate_limited, 1),
+            Some(Duration::from_millis(20))
+        );
+        let unavailable = server_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(Duration::from_millis(30)),
+        );
+        assert_eq!(
+            policy.delay(&unavailable, 1),
+            Some(Duration::from_millis(30))
+        );
+        // Waiting longer than we're willing to, let the caller know instead
+        let too_long = server_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Some(Duration::from_secs(60)),
+        );
+        assert!(policy.delay(&too_long, 1).is_none());
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let attempts = AtomicU32::new(0);
+        let result = policy(3)
+            .retry(|| async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(server_error(StatusCode::BAD_GATEWAY, None)),
+                    _ => Ok("page"),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "page");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy(2)
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(server_error(StatusCode::BAD_GATEWAY, None))
+            })
+            .await;
+        assert!(matches!(result, Err(JamfClientError::ServerError { .. })));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
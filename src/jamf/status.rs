@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client::{JamfClient, JamfClientTrait},
+    models::JamfAuthDetails,
+    provider::{ComputerProvider, MobileDeviceProvider},
+};
+
+/// Whether a Jamf connection works and has everything we need from it
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ConnectionStatus {
+    pub authenticated: bool,
+    /// True if the connection can be used to fetch devices
+    pub ready: bool,
+    pub jamf_version: Option<String>,
+    /// Privileges held by the account, `None` if Jamf didn't tell us
+    pub privileges: Option<Vec<String>>,
+    /// Privileges required for fetching devices that the account doesn't hold
+    pub missing_privileges: Vec<String>,
+    /// What went wrong talking to Jamf, if anything
+    pub errors: Vec<String>,
+}
+
+impl ConnectionStatus {
+    pub fn unauthenticated(error: String) -> Self {
+        Self {
+            authenticated: false,
+            ready: false,
+            jamf_version: None,
+            privileges: None,
+            missing_privileges: vec![],
+            errors: vec![error],
+        }
+    }
+}
+
+fn account_privileges(details: JamfAuthDetails) -> Vec<String> {
+    let mut privileges = details
+        .account
+        .into_iter()
+        .flat_map(|a| a.privileges)
+        .chain(
+            details
+                .account_groups
+                .into_iter()
+                .flat_map(|g| g.privileges),
+        )
+        .collect::<Vec<_>>();
+    privileges.sort();
+    privileges.dedup();
+    privileges
+}
+
+/// Check an authenticated connection, reporting the Jamf version and any missing privileges
+pub(crate) async fn check_connection(jamf_client: &JamfClient) -> ConnectionStatus {
+    let mut errors = vec![];
+    let jamf_version = jamf_client
+        .get_jamf_pro_version()
+        .await
+        .map(|v| v.version)
+        .map_err(|e| errors.push(format!("Failed to get Jamf version: {}", e)))
+        .ok();
+    let privileges = jamf_client
+        .get_auth_details()
+        .await
+        .map(account_privileges)
+        .map_err(|e| errors.push(format!("Failed to get account privileges: {}", e)))
+        .ok();
+
+    let missing_privileges = privileges
+        .as_ref()
+        .map(|privileges| {
+            let mut missing = ComputerProvider::REQUIRED_PRIVILEGES
+                .iter()
+                .chain(MobileDeviceProvider::REQUIRED_PRIVILEGES.iter())
+                .filter(|required| !privileges.iter().any(|p| p == *required))
+                .map(|required| required.to_string())
+                .collect::<Vec<_>>();
+            // Both providers need the software updates
+            missing.sort();
+            missing.dedup();
+            missing
+        })
+        .unwrap_or_default();
+
+    ConnectionStatus {
+        authenticated: true,
+        // NOTE: If Jamf wouldn't tell us the privileges we can't promise anything
+        ready: privileges.is_some() && missing_privileges.is_empty(),
+        jamf_version,
+        privileges,
+        missing_privileges,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jamf::{
+        client::{JamfClient, MockJamfClientTrait},
+        models::{JamfAccount, JamfAccountGroup, JamfAuthDetails, JamfProVersion},
+    };
+
+    use super::check_connection;
+
+    fn client_mock(privileges: Vec<&str>, group_privileges: Vec<&str>) -> MockJamfClientTrait {
+        let mut client_mock = MockJamfClientTrait::new();
+        client_mock.expect_get_jamf_pro_version().return_once(|| {
+            Ok(JamfProVersion {
+                version: "11.3.0-t1700000000".to_string(),
+            })
+        });
+        let details = JamfAuthDetails {
+            account: Some(JamfAccount {
+                username: Some("user".to_string()),
+                privileges: privileges.into_iter().map(str::to_string).collect(),
+            }),
+            account_groups: vec![JamfAccountGroup {
+                privileges: group_privileges.into_iter().map(str::to_string).collect(),
+            }],
+        };
+        client_mock
+            .expect_get_auth_details()
+            .return_once(|| Ok(details));
+        client_mock
+    }
+
+    #[tokio::test]
+    async fn connection_ready() {
+        // Privileges granted through a group count as well
+        let jamf_client = JamfClient::Mock(client_mock(
+            vec!["Read Computers", "Read Mobile Devices"],
+            vec!["Read Managed Software Updates", "Read Computers"],
+        ));
+        let status = check_connection(&jamf_client).await;
+        assert!(status.authenticated);
+        assert!(status.ready);
+        assert_eq!(status.jamf_version.as_deref(), Some("11.3.0-t1700000000"));
+        assert_eq!(
+            status.privileges,
+            Some(vec![
+                "Read Computers".to_string(),
+                "Read Managed Software Updates".to_string(),
+                "Read Mobile Devices".to_string()
+            ])
+        );
+        assert!(status.missing_privileges.is_empty());
+        assert!(status.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_privileges_flagged() {
+        let jamf_client = JamfClient::Mock(client_mock(vec!["Read Computers"], vec![]));
+        let status = check_connection(&jamf_client).await;
+        assert!(status.authenticated);
+        assert!(!status.ready);
+        assert_eq!(
+            status.missing_privileges,
+            vec![
+                "Read Managed Software Updates".to_string(),
+                "Read Mobile Devices".to_string()
+            ]
+        );
+    }
+}
@@ -1,10 +1,28 @@
+use std::{env, path::PathBuf, sync::Arc};
+
+use api_auth::{ApiAuth, Scope};
 use axum::{
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
+use dotenv::dotenv;
 
+pub mod api_auth;
 pub mod jamf;
 pub mod routes;
+pub mod state;
+pub mod store;
+use jamf::{
+    applications::{ApplicationIndex, ApplicationSearchConfig},
+    cache::DevicesCache,
+    config::JamfClientConfig,
+    extension_attributes::ExtensionAttributesConfig,
+    posture::StalenessConfig,
+    registry::JamfClientRegistry,
+};
+use state::AppState;
+use store::{api_keys::ApiKeyStore, connections::ConnectionStore, encryption::EnvelopeKey};
 use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer};
 use tracing::{info, Level};
 use tracing_subscriber::fmt;
@@ -14,9 +32,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilte
 async fn main() {
     // Set up tracing subscriber so we can see logs in stdout
     tracing_subscriber::registry()
-        .with(EnvFilter::new(
-            "info,tower_http=debug,axum=debug".to_string(),
-        ))
+        .with(EnvFilter::new("info,tower_http=debug,axum=debug"))
         .with(
             fmt::layer()
                 .with_target(true)
@@ -26,21 +42,132 @@ async fn main() {
         )
         .init();
 
+    // Jamf credentials are encrypted at rest with an envelope key that has to be supplied at startup
+    dotenv().ok();
+    let envelope_key = EnvelopeKey::from_base64(
+        &env::var("CREDENTIAL_STORE_KEY").expect("Please set credential_store_key env var"),
+    )
+    .expect("CREDENTIAL_STORE_KEY must be a base64 encoded 256 bit key");
+    let store_path = env::var("CREDENTIAL_STORE_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("credential_store.json"));
+    // To rotate the envelope key, start once with the old key in CREDENTIAL_STORE_PREVIOUS_KEY
+    let connections = match env::var("CREDENTIAL_STORE_PREVIOUS_KEY") {
+        Ok(previous_key) => {
+            let previous_key = EnvelopeKey::from_base64(&previous_key)
+                .expect("CREDENTIAL_STORE_PREVIOUS_KEY must be a base64 encoded 256 bit key");
+            let connections = ConnectionStore::open(previous_key, Some(store_path))
+                .await
+                .expect("Failed to open credential store with the previous key");
+            connections
+                .rotate_envelope_key(envelope_key)
+                .await
+                .expect("Failed to rotate credential store envelope key");
+            connections
+        }
+        Err(_) => ConnectionStore::open(envelope_key, Some(store_path))
+            .await
+            .expect("Failed to open credential store"),
+    };
+
+    // Callers of our API authenticate with API keys or JWTs, ADMIN_API_KEY is used to mint the first API keys
+    let api_key_path = env::var("API_KEY_STORE_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("api_keys.json"));
+    let api_keys = ApiKeyStore::open(Some(api_key_path))
+        .await
+        .expect("Failed to open API key store");
+    let api_auth = ApiAuth::new(env::var("JWT_SECRET").ok(), env::var("ADMIN_API_KEY").ok());
+
+    // A bad root CA, proxy or pin would otherwise only show up as failing Jamf requests
+    let jamf_config = JamfClientConfig::from_env()
+        .unwrap_or_else(|e| panic!("Invalid Jamf transport defaults: {}", e));
+
+    let application_search = ApplicationSearchConfig::from_env();
+    let state = AppState {
+        connections: Arc::new(connections),
+        jamf_clients: Arc::new(JamfClientRegistry::new(jamf_config)),
+        devices_cache: Arc::new(DevicesCache::default()),
+        extension_attributes: Arc::new(ExtensionAttributesConfig::from_env()),
+        staleness: StalenessConfig::from_env(),
+        application_search,
+        application_index: Arc::new(ApplicationIndex::new(application_search.max_age())),
+        api_keys: Arc::new(api_keys),
+        api_auth: Arc::new(api_auth),
+    };
+    let scope = |scope: Scope| middleware::from_fn_with_state(scope, api_auth::require_scope);
+
+    // Jamf routes are scoped to a tenant, each tenant has its own Jamf connections
+    let jamf_routes = Router::new()
+        .route(
+            "/credentials",
+            post(routes::credentials::credentials)
+                .route_layer(scope(Scope::CredentialsWrite))
+                .merge(
+                    get(routes::credentials::list_credentials)
+                        .route_layer(scope(Scope::CredentialsRead)),
+                ),
+        )
+        .route(
+            "/credentials/:connection_id",
+            put(routes::credentials::rotate_credentials)
+                .delete(routes::credentials::delete_credentials)
+                .route_layer(scope(Scope::CredentialsWrite)),
+        )
+        .route(
+            "/connections/:connection_id/status",
+            get(routes::connections::connection_status).route_layer(scope(Scope::CredentialsRead)),
+        )
+        .route(
+            "/devices",
+            get(routes::devices::devices).route_layer(scope(Scope::DevicesRead)),
+        )
+        .route(
+            "/devices/:device_id",
+            get(routes::devices::device).route_layer(scope(Scope::DevicesRead)),
+        )
+        .route(
+            "/users/:email/devices",
+            get(routes::devices::user_devices).route_layer(scope(Scope::DevicesRead)),
+        )
+        .route(
+            "/applications",
+            get(routes::applications::applications).route_layer(scope(Scope::DevicesRead)),
+        )
+        .route(
+            "/mobile-devices/:device_id",
+            get(routes::devices::mobile_device).route_layer(scope(Scope::DevicesRead)),
+        );
+
+    // Everything under /api requires authentication
+    let api_routes = Router::new()
+        .nest("/tenants/:tenant_id/jamf", jamf_routes)
+        .route(
+            "/keys",
+            post(routes::api_keys::mint_api_key)
+                .get(routes::api_keys::list_api_keys)
+                .route_layer(scope(Scope::Admin)),
+        )
+        .route(
+            "/keys/:key_id",
+            delete(routes::api_keys::revoke_api_key).route_layer(scope(Scope::Admin)),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api_auth::authenticate,
+        ));
+
     // Create routes
     let app = Router::new()
         .route("/", get(routes::hello::hello_world))
-        .route(
-            "/api/jamf/credentials",
-            post(routes::credentials::credentials),
-        )
-        // NOTE: Because there are only computers in the Jamf instance, here I only get computers and not mobile devices
-        .route("/api/jamf/devices", get(routes::devices::devices))
+        .nest("/api", api_routes)
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
                 .on_request(DefaultOnRequest::new().level(Level::INFO))
                 .on_response(DefaultOnResponse::new().level(Level::INFO)),
-        );
+        )
+        .with_state(state);
 
     info!("Listening on 0.0.0.0:3000");
     axum::Server::bind(&"0.0.0.0:3000".parse().unwrap())
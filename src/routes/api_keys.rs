@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    Extension,
+};
+use chrono::{DateTime, Duration, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+
+use crate::{
+    api_auth::{Principal, Scope},
+    state::AppState,
+    store::api_keys::ApiKeySummary,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiKeyInput {
+    /// Human readable name, e.g. what the key is used for
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Restrict the key to a single tenant, leave out for a key that works across tenants
+    pub tenant_id: Option<String>,
+    /// Leave out for a key that never expires
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ApiKeyOutput {
+    #[serde(flatten)]
+    pub key: ApiKeySummary,
+    /// The key to send in requests, it is only ever returned here
+    pub api_key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ApiKeyListOutput {
+    pub keys: Vec<ApiKeySummary>,
+}
+
+/// When a key minted at `now` expires, `None` if that's further out than a timestamp can be
+fn expires_at(now: DateTime<Utc>, expires_in_days: i64) -> Option<DateTime<Utc>> {
+    // `Duration::days` panics when out of range, milliseconds of any i64 are fine
+    let expires_in = Duration::milliseconds(expires_in_days.checked_mul(24 * 60 * 60 * 1000)?);
+    now.checked_add_signed(expires_in)
+}
+
+#[instrument(skip(state, principal))]
+pub async fn mint_api_key(
+    State(state): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Json(input): Json<ApiKeyInput>,
+) -> Result<Json<ApiKeyOutput>, StatusCode> {
+    if input.scopes.is_empty() || input.expires_in_days.is_some_and(|days| days <= 0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !principal.may_grant(&input.scopes, input.tenant_id.as_deref()) {
+        info!(
+            "Denied {} minting a key with {:?} for tenant {:?}",
+            principal.subject, input.scopes, input.tenant_id
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let expires_at = input
+        .expires_in_days
+        .map(|days| expires_at(Utc::now(), days).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()?;
+    let (key, api_key) = state
+        .api_keys
+        .mint(input.name, input.scopes, input.tenant_id, expires_at)
+        .await
+        .map_err(|e| {
+            error!("Failed to store API key: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    info!("{} minted API key {}", principal.subject, key.key_id);
+    Ok(Json(ApiKeyOutput { key, api_key }))
+}
+
+pub async fn list_api_keys(State(state): State<AppState>) -> Json<ApiKeyListOutput> {
+    Json(ApiKeyListOutput {
+        keys: state.api_keys.list().await,
+    })
+}
+
+#[instrument(skip(state, principal))]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Extension(principal): Extension<Principal>,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKeySummary>, StatusCode> {
+    let key = state
+        .api_keys
+        .revoke(&key_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke API key: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    info!("{} revoked API key {}", principal.subject, key.key_id);
+    Ok(Json(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::expires_at;
+
+    #[test]
+    fn expiry_out_of_range_rejected() {
+        let now = Utc::now();
+        assert_eq!(expires_at(now, 30), Some(now + Duration::days(30)));
+        assert_eq!(expires_at(now, i64::MAX), None);
+        // Fits in a duration, but not once added to now
+        assert_eq!(expires_at(now, 100_000_000_000), None);
+    }
+}
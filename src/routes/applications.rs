@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Json, Response},
+};
+use http::StatusCode;
+use serde::Deserialize;
+use tracing::{error, info, instrument, warn};
+use version_compare::Version;
+
+use crate::{
+    jamf::{applications::ApplicationQuery, client::JamfClient, provider::ComputerProvider},
+    routes::{
+        devices::{jamf_error_response, resolve_connection, JamfErrorOutput},
+        TenantPath,
+    },
+    state::AppState,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ApplicationsParams {
+    /// Connection returned by `POST /api/tenants/:tenant_id/jamf/credentials`
+    /// Can be left out if the tenant only has a single connection
+    pub connection_id: Option<String>,
+    /// e.g. `com.tinyspeck.slackmacgap`
+    pub bundle_id: String,
+    /// Only Macs with an older version of the app, compared as versions so `4.9` is below `4.10`
+    pub below_version: Option<String>,
+}
+
+/// Which Macs have an app installed, e.g. to find every Mac with an app below a patched version
+/// Searches the connection's last app scan, Macs are only scanned again once it is older than
+/// `JAMF_APPLICATION_INDEX_MAX_AGE_SECS`
+#[instrument(skip(state))]
+pub async fn applications(
+    State(state): State<AppState>,
+    Path(TenantPath { tenant_id }): Path<TenantPath>,
+    Query(params): Query<ApplicationsParams>,
+) -> Result<Response, Response> {
+    if params.bundle_id.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "bundle_id can't be empty").into_response());
+    }
+    if let Some(below_version) = &params.below_version {
+        if Version::from(below_version).is_none() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("{:?} isn't a version", below_version),
+            )
+                .into_response());
+        }
+    }
+    let connection = resolve_connection(&state, &tenant_id, params.connection_id.as_deref())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    info!("Using Jamf connection {}", connection.id);
+
+    let jamf_client =
+        JamfClient::Impl(state.jamf_clients.client_for(&connection).map_err(|e| {
+            error!("Failed to build Jamf transport: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?);
+    let computer_provider = ComputerProvider {
+        jamf_client,
+        extension_attributes: state.extension_attributes.clone(),
+        staleness: state.staleness,
+    };
+    // The first search after the max age scans every Mac again, give up on one that would keep
+    // Jamf busy for too long
+    let timeout = state.application_search.timeout();
+    let query = ApplicationQuery {
+        bundle_id: params.bundle_id,
+        below_version: params.below_version,
+    };
+    let applications = tokio::time::timeout(
+        timeout,
+        state.application_index.search(&connection, &query, || {
+            computer_provider.fetch_application_inventory()
+        }),
+    )
+    .await
+    .map_err(|_| {
+        warn!("Application search timed out after {:?}", timeout);
+        (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(JamfErrorOutput {
+                error: "application_search_timeout".to_string(),
+                message: format!(
+                    "Scanning every Mac's apps took longer than {}s",
+                    timeout.as_secs()
+                ),
+            }),
+        )
+            .into_response()
+    })?
+    .map_err(jamf_error_response)?;
+    Ok(Json(applications).into_response())
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+
+use crate::{
+    jamf::{
+        client::JamfClient,
+        status::{check_connection, ConnectionStatus},
+    },
+    routes::ConnectionPath,
+    state::AppState,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct ConnectionStatusOutput {
+    pub connection_id: String,
+    pub url: String,
+    #[serde(flatten)]
+    pub status: ConnectionStatus,
+}
+
+/// Check whether a stored connection still works and holds the privileges we need
+#[instrument(skip(state))]
+pub async fn connection_status(
+    State(state): State<AppState>,
+    Path(ConnectionPath {
+        tenant_id,
+        connection_id,
+    }): Path<ConnectionPath>,
+) -> Result<Json<ConnectionStatusOutput>, StatusCode> {
+    let connection = state
+        .connections
+        .get(&tenant_id, &connection_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load Jamf connection: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let client = state
+        .jamf_clients
+        .new_client(
+            &tenant_id,
+            connection.credentials.clone(),
+            &connection.jamf_url,
+            &connection.transport,
+        )
+        .map_err(|e| {
+            error!("Failed to build Jamf transport: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    // Authenticate from scratch instead of using the cached token, so credentials that were
+    // changed or disabled in Jamf are caught
+    let status = match client.authenticate().await {
+        Ok(()) => {
+            let status = check_connection(&JamfClient::Impl(client.clone())).await;
+            client.revoke().await;
+            status
+        }
+        Err(e) => ConnectionStatus::unauthenticated(format!("Failed to authenticate: {}", e)),
+    };
+    info!(
+        "Jamf connection {} authenticated: {}, ready: {}",
+        connection.id, status.authenticated, status.ready
+    );
+    Ok(Json(ConnectionStatusOutput {
+        connection_id: connection.id,
+        url: connection.jamf_url,
+        status,
+    }))
+}
@@ -1,25 +1,233 @@
-use axum::response::Json;
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use http::StatusCode;
 use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+
+use crate::{
+    jamf::{
+        auth::JamfCredentials,
+        client::{JamfClientError, JamfClientImpl},
+        transport::TransportConfig,
+    },
+    routes::{ConnectionPath, TenantPath},
+    state::AppState,
+    store::connections::{ConnectionSummary, StoreError},
+};
+
+/// Either a Jamf user account or an API client, distinguished by which fields are present
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CredentialsSecret {
+    Basic {
+        username: String,
+        password: String,
+    },
+    OAuthClientCredentials {
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+impl From<CredentialsSecret> for JamfCredentials {
+    fn from(secret: CredentialsSecret) -> Self {
+        match secret {
+            CredentialsSecret::Basic { username, password } => {
+                JamfCredentials::Basic { username, password }
+            }
+            CredentialsSecret::OAuthClientCredentials {
+                client_id,
+                client_secret,
+            } => JamfCredentials::OAuthClientCredentials {
+                client_id,
+                client_secret,
+            },
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct CredentialsInput {
-    pub username: String,
-    pub password: String,
-    pub url: String
+    #[serde(flatten)]
+    pub secret: CredentialsSecret,
+    pub url: String,
+    /// Timeouts, proxy and TLS settings, left out fields use the server defaults
+    #[serde(default)]
+    pub transport: TransportConfig,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RotateCredentialsInput {
+    #[serde(flatten)]
+    pub secret: CredentialsSecret,
+    /// Replaces the connection's transport, the current one is kept if left out
+    pub transport: Option<TransportConfig>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct CredentialsOutput {
-    pub username: String,
-    pub password: String,
-    pub url: String
+    /// Opaque id to pass to the other Jamf endpoints, the credentials themselves are never returned
+    pub connection_id: String,
+    /// Username or API client id the connection authenticates as
+    pub principal: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CredentialsListOutput {
+    pub connections: Vec<ConnectionSummary>,
 }
 
-pub async fn credentials(Json(credentials): Json<CredentialsInput>) -> Json<CredentialsOutput> {
-    // NOTE: For the real app, this would probably authenticate with Jamf and return a bearer token that could be included in subsequent requests
-    Json(CredentialsOutput {
-        username: credentials.username,
-        password: credentials.password,
-        url: credentials.url
+fn store_error(e: StoreError) -> StatusCode {
+    error!("Credential store failed: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+// Creating the client authenticates with Jamf, so we never store credentials that don't work
+async fn verify_credentials(
+    state: &AppState,
+    tenant_id: &str,
+    credentials: &JamfCredentials,
+    jamf_url: &str,
+    transport: &TransportConfig,
+) -> Result<JamfClientImpl, StatusCode> {
+    transport.validate().map_err(|e| {
+        error!("Invalid Jamf transport for {}: {}", jamf_url, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let client = state
+        .jamf_clients
+        .new_client(tenant_id, credentials.clone(), jamf_url, transport)
+        .map_err(|e| {
+            error!("Failed to build Jamf transport for {}: {}", jamf_url, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    client.authenticate().await.map(|_| client).map_err(|e| {
+        error!("Failed to verify Jamf credentials for {}: {}", jamf_url, e);
+        match e {
+            // Wrong credentials, or a URL that isn't a Jamf instance
+            JamfClientError::AuthenticationFailed { .. }
+            | JamfClientError::PermissionDenied { .. }
+            | JamfClientError::NotFound { .. }
+            | JamfClientError::MalformedPayload { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    })
+}
+
+#[instrument(skip(state, input))]
+pub async fn credentials(
+    State(state): State<AppState>,
+    Path(TenantPath { tenant_id }): Path<TenantPath>,
+    Json(input): Json<CredentialsInput>,
+) -> Result<Json<CredentialsOutput>, StatusCode> {
+    let credentials = JamfCredentials::from(input.secret);
+    let jamf_url = input.url.trim_end_matches('/').to_string();
+    let client = verify_credentials(
+        &state,
+        &tenant_id,
+        &credentials,
+        &jamf_url,
+        &input.transport,
+    )
+    .await?;
+
+    let connection = state
+        .connections
+        .insert(&tenant_id, jamf_url, input.transport, credentials)
+        .await
+        .map_err(store_error)?;
+    // Keep the client we just authenticated with so the first devices request doesn't authenticate again
+    state.jamf_clients.insert(&connection, client);
+    info!("Stored Jamf connection {}", connection.id);
+    Ok(Json(CredentialsOutput {
+        principal: connection.credentials.principal().to_string(),
+        connection_id: connection.id,
+        url: connection.jamf_url,
+    }))
+}
+
+/// List the tenant's stored connections, secrets are never included
+#[instrument(skip(state))]
+pub async fn list_credentials(
+    State(state): State<AppState>,
+    Path(TenantPath { tenant_id }): Path<TenantPath>,
+) -> Json<CredentialsListOutput> {
+    Json(CredentialsListOutput {
+        connections: state.connections.list(&tenant_id).await,
     })
 }
+
+/// Swap the credentials of an existing connection, the new credentials are verified before storing them
+#[instrument(skip(state, input))]
+pub async fn rotate_credentials(
+    State(state): State<AppState>,
+    Path(ConnectionPath {
+        tenant_id,
+        connection_id,
+    }): Path<ConnectionPath>,
+    Json(input): Json<RotateCredentialsInput>,
+) -> Result<Json<CredentialsOutput>, StatusCode> {
+    let existing = state
+        .connections
+        .get(&tenant_id, &connection_id)
+        .await
+        .map_err(store_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let credentials = JamfCredentials::from(input.secret);
+    let client = verify_credentials(
+        &state,
+        &tenant_id,
+        &credentials,
+        &existing.jamf_url,
+        input.transport.as_ref().unwrap_or(&existing.transport),
+    )
+    .await?;
+
+    let connection = state
+        .connections
+        .rotate(&tenant_id, &connection_id, input.transport, credentials)
+        .await
+        .map_err(store_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // Requests that already started keep the old client, its token is invalidated once they are done
+    if let Some(previous) = state.jamf_clients.insert(&connection, client) {
+        previous.revoke_when_idle();
+    }
+    info!("Rotated credentials for Jamf connection {}", connection.id);
+    Ok(Json(CredentialsOutput {
+        principal: connection.credentials.principal().to_string(),
+        connection_id: connection.id,
+        url: connection.jamf_url,
+    }))
+}
+
+/// Delete a connection, its secrets are purged from the store and its Jamf token is invalidated
+#[instrument(skip(state))]
+pub async fn delete_credentials(
+    State(state): State<AppState>,
+    Path(ConnectionPath {
+        tenant_id,
+        connection_id,
+    }): Path<ConnectionPath>,
+) -> Result<StatusCode, StatusCode> {
+    state
+        .connections
+        .delete(&tenant_id, &connection_id)
+        .await
+        .map_err(store_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(client) = state.jamf_clients.remove(&tenant_id, &connection_id) {
+        client.revoke().await;
+    }
+    state
+        .devices_cache
+        .remove_connection(&tenant_id, &connection_id);
+    state
+        .application_index
+        .remove_connection(&tenant_id, &connection_id);
+    info!("Deleted Jamf connection {}", connection_id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,41 +1,695 @@
-use std::env;
+use std::pin::Pin;
 
-use axum::response::Json;
-use http::StatusCode;
+use axum::{
+    body::StreamBody,
+    extract::{Path, Query, RawQuery, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use futures::{future, stream, try_join, StreamExt};
+use http::{
+    header::{ACCEPT, CONTENT_TYPE, RETRY_AFTER},
+    HeaderMap, StatusCode,
+};
+use serde::{Deserialize, Serialize};
 
-use tracing::{error, info, instrument};
+use tracing::{error, info, instrument, warn};
 
-use crate::jamf::{
-    client::{JamfClient, JamfClientImpl},
-    provider::{ComputerProvider, DevicesOutput},
+use crate::{
+    jamf::{
+        client::{JamfClient, JamfClientError},
+        extension_attributes::ExtensionAttributeFilter,
+        provider::{ComputerProvider, Device, DevicesOutput, MobileDevice, MobileDeviceProvider},
+        query::{
+            parse_sort, ComputerField, ComputerInventoryQuery, Filter, MobileDeviceInventoryQuery,
+        },
+    },
+    routes::{DevicePath, TenantPath, UserPath},
+    state::AppState,
+    store::connections::JamfConnection,
 };
-use dotenv::dotenv;
-
-#[instrument]
-pub async fn devices() -> Result<Json<DevicesOutput>, StatusCode> {
-    dotenv().ok();
-    // NOTE: In a real app the request would probably come with a bearer token
-    // For now, just get everything from env variables - in the future can use postgres
-    let username = env::var("USERNAME").expect("Please set username env var");
-    let password = env::var("PASSWORD").expect("Please set password env var");
-    let jamf_url = env::var("JAMF_URL").expect("Please set jamf_url env var");
-    info!("Creating Jamf Client for request!");
-
-    let jamf_client = JamfClient::Impl(
-        JamfClientImpl::new(username, password, jamf_url)
-            .await
-            .map_err(|e| {
-                error!("Failed to create Jamf Client for request: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?,
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DevicesParams {
+    /// Connection returned by `POST /api/tenants/:tenant_id/jamf/credentials`
+    /// Can be left out if the tenant only has a single connection
+    pub connection_id: Option<String>,
+    /// `ndjson` streams one device per line, can also be requested with `Accept: application/x-ndjson`
+    pub format: Option<DevicesFormat>,
+    /// Device name, `*` matches anything
+    pub name: Option<String>,
+    pub serial_number: Option<String>,
+    /// Comma separated list of OS versions
+    pub os_version: Option<String>,
+    /// Only devices that reported inventory after this time
+    pub reported_after: Option<DateTime<Utc>>,
+    pub reported_before: Option<DateTime<Utc>>,
+    /// Jamf style sort, e.g. `general.reportDate:desc,id:asc`
+    pub sort: Option<String>,
+}
+
+/// The `name:value` filters on configured extension attributes, one `extension_attribute` param
+/// each so values can contain commas, e.g. `?extension_attribute=cost_center:R%26D`
+/// Not part of `DevicesParams` since `Query` can't deserialize a repeated param
+fn extension_attribute_params(query: Option<&str>) -> Vec<String> {
+    form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .filter(|(key, _)| key == "extension_attribute")
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+/// Turn the filter params into a query Jamf evaluates, so we only download the devices asked for
+fn inventory_query(params: &DevicesParams) -> Result<ComputerInventoryQuery, String> {
+    let mut filters = vec![];
+    if let Some(name) = &params.name {
+        filters.push(Filter::eq(ComputerField::GeneralName, name.as_str()));
+    }
+    if let Some(serial_number) = &params.serial_number {
+        filters.push(Filter::eq(
+            ComputerField::HardwareSerialNumber,
+            serial_number.as_str(),
+        ));
+    }
+    if let Some(os_version) = &params.os_version {
+        filters.push(Filter::is_in(
+            ComputerField::OperatingSystemVersion,
+            os_version.split(',').map(str::trim),
+        ));
+    }
+    if let Some(reported_after) = params.reported_after {
+        filters.push(Filter::gt(ComputerField::GeneralReportDate, reported_after));
+    }
+    if let Some(reported_before) = params.reported_before {
+        filters.push(Filter::lt(
+            ComputerField::GeneralReportDate,
+            reported_before,
+        ));
+    }
+
+    let mut query = filters.into_iter().fold(
+        ComputerInventoryQuery::default(),
+        ComputerInventoryQuery::filter,
     );
-    let computer_provider = ComputerProvider { jamf_client };
-    // NOTE: If we had mobile devices in the Jamf account, I would create a MobileDeviceProvider as well
+    for (field, direction) in params
+        .sort
+        .as_deref()
+        .map(parse_sort)
+        .transpose()?
+        .into_iter()
+        .flatten()
+    {
+        query = query.sort_by(field, direction);
+    }
+    Ok(query)
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DevicesFormat {
+    Json,
+    Ndjson,
+}
+
+static NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+fn wants_ndjson(params: &DevicesParams, headers: &HeaderMap) -> bool {
+    match params.format {
+        Some(format) => format == DevicesFormat::Ndjson,
+        None => headers
+            .get(ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| accept.contains(NDJSON_CONTENT_TYPE)),
+    }
+}
+
+/// Accounts that may only read computers still get their computers, just without mobile devices
+fn skip_denied_mobile_devices<T: Default>(
+    result: Result<T, JamfClientError>,
+) -> Result<T, JamfClientError> {
+    match result {
+        Err(e @ JamfClientError::PermissionDenied { .. }) => {
+            warn!("Skipping mobile devices: {}", e);
+            Ok(T::default())
+        }
+        result => result,
+    }
+}
+
+/// Stream devices as newline delimited JSON, so large fleets never sit in memory and clients can
+/// start rendering right away, mobile devices follow the computers
+/// Once the first device is sent the status can't change anymore, so a later Jamf failure is sent
+/// as a final `{"error": ..., "message": ...}` line
+async fn stream_devices(
+    computer_provider: ComputerProvider,
+    mobile_device_provider: MobileDeviceProvider,
+    query: ComputerInventoryQuery,
+    mobile_device_query: Option<MobileDeviceInventoryQuery>,
+    extension_attribute_filters: Vec<ExtensionAttributeFilter>,
+) -> Result<Response, Response> {
+    let mut computers = computer_provider
+        .stream_computers(query)
+        .await
+        .map_err(jamf_error_response)?
+        .map(|computer| computer.map(Device::Computer))
+        .filter(move |computer| {
+            future::ready(computer.as_ref().map_or(true, |c| {
+                c.matches_extension_attributes(&extension_attribute_filters)
+            }))
+        })
+        .peekable();
+    // Failing on the first page (e.g. missing privileges) still gets a proper status
+    if let Some(Err(e)) = Pin::new(&mut computers).next_if(Result::is_err).await {
+        return Err(jamf_error_response(e));
+    }
+
+    // Only asked for once the computers are done
+    let mobile_devices = stream::once(async move {
+        let Some(query) = mobile_device_query else {
+            return stream::empty().boxed();
+        };
+        let mobile_devices = mobile_device_provider.stream_mobile_devices(query).await;
+        match skip_denied_mobile_devices(mobile_devices.map(Some)) {
+            Ok(Some(mobile_devices)) => mobile_devices
+                .filter_map(|mobile_device| {
+                    future::ready(skip_denied_mobile_devices(mobile_device.map(Some)).transpose())
+                })
+                .map(|mobile_device| mobile_device.map(Device::MobileDevice))
+                .boxed(),
+            Ok(None) => stream::empty().boxed(),
+            Err(e) => stream::once(future::ready(Err(e))).boxed(),
+        }
+    })
+    .flatten();
+
+    let lines = computers
+        .chain(mobile_devices)
+        .scan(false, |failed, device| {
+            if *failed {
+                return future::ready(None);
+            }
+            let line = match device {
+                Ok(device) => serde_json::to_vec(&device),
+                Err(e) => {
+                    *failed = true;
+                    serde_json::to_vec(&jamf_error_output(&e).1)
+                }
+            };
+            future::ready(Some(line.map(|mut line| {
+                line.push(b'\n');
+                line
+            })))
+        })
+        .boxed();
+    Ok((
+        [(CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        StreamBody::new(lines),
+    )
+        .into_response())
+}
+
+/// Computers followed by mobile devices, mobile devices are left out when the filter doesn't apply
+/// to them
+async fn fetch_devices(
+    computer_provider: &ComputerProvider,
+    mobile_device_provider: &MobileDeviceProvider,
+    query: ComputerInventoryQuery,
+    mobile_device_query: Option<MobileDeviceInventoryQuery>,
+) -> Result<DevicesOutput, JamfClientError> {
+    let fetch_mobile_devices = async {
+        match mobile_device_query {
+            Some(query) => {
+                skip_denied_mobile_devices(mobile_device_provider.fetch_mobile_devices(query).await)
+            }
+            None => Ok(Vec::<MobileDevice>::new()),
+        }
+    };
+    let (computers, mobile_devices) = try_join!(
+        computer_provider.fetch_computers(query),
+        fetch_mobile_devices
+    )?;
+    Ok(computers.with_mobile_devices(mobile_devices))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JamfErrorOutput {
+    /// Stable identifier of what went wrong, e.g. `jamf_permission_denied`
+    pub error: String,
+    pub message: String,
+}
+
+/// Map a failed Jamf call to the response we give our caller
+/// Problems the customer has to fix in Jamf (credentials, privileges) are a 424 so they aren't confused
+/// with failing to authenticate with our own API
+pub(crate) fn jamf_error_response(e: JamfClientError) -> Response {
+    let (status, output) = jamf_error_output(&e);
+    let mut response = (status, Json(output)).into_response();
+    // Pass Jamf's backoff on to our caller
+    let retry_after = match e {
+        JamfClientError::RateLimited { retry_after, .. }
+        | JamfClientError::ServerError { retry_after, .. } => retry_after,
+        JamfClientError::CircuitOpen { retry_after, .. } => Some(retry_after),
+        _ => None,
+    };
+    if let Some(retry_after) = retry_after {
+        // Round up, a caller coming back early would only hit the open circuit again
+        let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        response.headers_mut().insert(RETRY_AFTER, secs.into());
+    }
+    response
+}
+
+fn jamf_error_output(e: &JamfClientError) -> (StatusCode, JamfErrorOutput) {
+    let (status, error) = match e {
+        JamfClientError::AuthenticationFailed { .. } => {
+            (StatusCode::FAILED_DEPENDENCY, "jamf_authentication_failed")
+        }
+        JamfClientError::PermissionDenied { .. } => {
+            (StatusCode::FAILED_DEPENDENCY, "jamf_permission_denied")
+        }
+        JamfClientError::NotFound { .. } => (StatusCode::NOT_FOUND, "jamf_not_found"),
+        JamfClientError::Revoked => (StatusCode::NOT_FOUND, "jamf_connection_revoked"),
+        JamfClientError::RateLimited { .. } => (StatusCode::TOO_MANY_REQUESTS, "jamf_rate_limited"),
+        JamfClientError::ReqwestError(e) if e.is_timeout() => {
+            (StatusCode::GATEWAY_TIMEOUT, "jamf_timeout")
+        }
+        JamfClientError::ReqwestError(_) => (StatusCode::BAD_GATEWAY, "jamf_unreachable"),
+        JamfClientError::ServerError { .. } => (StatusCode::BAD_GATEWAY, "jamf_server_error"),
+        JamfClientError::CircuitOpen { .. } => {
+            (StatusCode::SERVICE_UNAVAILABLE, "jamf_unavailable")
+        }
+        JamfClientError::UnexpectedStatus { .. } => {
+            (StatusCode::BAD_GATEWAY, "jamf_unexpected_status")
+        }
+        JamfClientError::MalformedPayload { .. } | JamfClientError::InvalidTokenExpiry(_) => {
+            (StatusCode::BAD_GATEWAY, "jamf_malformed_payload")
+        }
+    };
+    (
+        status,
+        JamfErrorOutput {
+            error: error.to_string(),
+            message: e.to_string(),
+        },
+    )
+}
+
+/// Find the tenant connection a request is for
+pub(crate) async fn resolve_connection(
+    state: &AppState,
+    tenant_id: &str,
+    connection_id: Option<&str>,
+) -> Result<JamfConnection, StatusCode> {
+    let connection_id = match connection_id {
+        Some(connection_id) => connection_id.to_string(),
+        None => {
+            let connections = state.connections.list(tenant_id).await;
+            match connections.as_slice() {
+                [connection] => connection.connection_id.clone(),
+                [] => return Err(StatusCode::NOT_FOUND),
+                // Ambiguous, the caller has to tell us which Jamf instance they mean
+                _ => return Err(StatusCode::BAD_REQUEST),
+            }
+        }
+    };
+    state
+        .connections
+        .get(tenant_id, &connection_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load Jamf connection: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[instrument(skip(state))]
+pub async fn devices(
+    State(state): State<AppState>,
+    Path(TenantPath { tenant_id }): Path<TenantPath>,
+    Query(params): Query<DevicesParams>,
+    RawQuery(raw_query): RawQuery,
+    headers: HeaderMap,
+) -> Result<Response, Response> {
+    let query =
+        inventory_query(&params).map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let extension_attribute_filters = state
+        .extension_attributes
+        .parse_filters(&extension_attribute_params(raw_query.as_deref()))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let connection = resolve_connection(&state, &tenant_id, params.connection_id.as_deref())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    info!("Using Jamf connection {}", connection.id);
+
+    let jamf_client = state.jamf_clients.client_for(&connection).map_err(|e| {
+        error!("Failed to build Jamf transport: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+    let computer_provider = ComputerProvider {
+        jamf_client: JamfClient::Impl(jamf_client.clone()),
+        extension_attributes: state.extension_attributes.clone(),
+        staleness: state.staleness,
+    };
+    let mobile_device_provider = MobileDeviceProvider {
+        jamf_client: JamfClient::Impl(jamf_client),
+        staleness: state.staleness,
+    };
 
-    let inventory = computer_provider.fetch_computers().await.map_err(|e| {
-        error!("Something went wrong fetching computer inventory: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+    if wants_ndjson(&params, &headers) {
+        // Mobile devices have no extension attributes, so they can't match a filter on them
+        let mobile_device_query = MobileDeviceInventoryQuery::matching(&query)
+            .filter(|_| extension_attribute_filters.is_empty());
+        return stream_devices(
+            computer_provider,
+            mobile_device_provider,
+            query,
+            mobile_device_query,
+            extension_attribute_filters,
+        )
+        .await;
+    }
+
+    // The cache holds what Jamf returned, so extension attribute filters apply to stale lists too
+    let devices_output = fetch_devices_or_stale(
+        &state,
+        &connection,
+        &computer_provider,
+        &mobile_device_provider,
+        query,
+    )
+    .await?
+    .filter_extension_attributes(&extension_attribute_filters);
+    Ok(Json(devices_output).into_response())
+}
+
+/// Fetch the devices, falling back to the last list we got for the same query while Jamf is down
+async fn fetch_devices_or_stale(
+    state: &AppState,
+    connection: &JamfConnection,
+    computer_provider: &ComputerProvider,
+    mobile_device_provider: &MobileDeviceProvider,
+    query: ComputerInventoryQuery,
+) -> Result<DevicesOutput, Response> {
+    let mobile_device_query = MobileDeviceInventoryQuery::matching(&query);
+    match fetch_devices(
+        computer_provider,
+        mobile_device_provider,
+        query.clone(),
+        mobile_device_query,
+    )
+    .await
+    {
+        Ok(devices_output) => {
+            state
+                .devices_cache
+                .insert(connection, &query, &devices_output);
+            Ok(devices_output)
+        }
+        // Rather show the last devices we saw than nothing while Jamf is down
+        Err(e) if e.is_unavailable() => match state.devices_cache.get_stale(connection, &query) {
+            Some(stale) => {
+                warn!("Jamf is unavailable, serving stale devices: {}", e);
+                Ok(stale)
+            }
+            None => Err(jamf_error_response(e)),
+        },
+        Err(e) => {
+            error!("Something went wrong fetching device inventory: {}", e);
+            Err(jamf_error_response(e))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DeviceParams {
+    /// Connection returned by `POST /api/tenants/:tenant_id/jamf/credentials`
+    /// Can be left out if the tenant only has a single connection
+    pub connection_id: Option<String>,
+}
+
+/// Jamf ids are numeric, anything else can't exist and mustn't end up in the Jamf path
+fn is_jamf_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit())
+}
+
+async fn device_jamf_client(
+    state: &AppState,
+    tenant_id: &str,
+    params: &DeviceParams,
+) -> Result<JamfClient, Response> {
+    let connection = resolve_connection(state, tenant_id, params.connection_id.as_deref())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    info!("Using Jamf connection {}", connection.id);
+
+    Ok(JamfClient::Impl(
+        state.jamf_clients.client_for(&connection).map_err(|e| {
+            error!("Failed to build Jamf transport: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?,
+    ))
+}
+
+#[instrument(skip(state))]
+pub async fn device(
+    State(state): State<AppState>,
+    Path(DevicePath {
+        tenant_id,
+        device_id,
+    }): Path<DevicePath>,
+    Query(params): Query<DeviceParams>,
+) -> Result<Response, Response> {
+    if !is_jamf_id(&device_id) {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    }
+    let jamf_client = device_jamf_client(&state, &tenant_id, &params).await?;
+    let computer_provider = ComputerProvider {
+        jamf_client,
+        extension_attributes: state.extension_attributes.clone(),
+        staleness: state.staleness,
+    };
+    let computer = computer_provider
+        .fetch_computer(&device_id)
+        .await
+        .map_err(jamf_error_response)?;
+    Ok(Json(computer).into_response())
+}
+
+#[instrument(skip(state))]
+pub async fn mobile_device(
+    State(state): State<AppState>,
+    Path(DevicePath {
+        tenant_id,
+        device_id,
+    }): Path<DevicePath>,
+    Query(params): Query<DeviceParams>,
+) -> Result<Response, Response> {
+    if !is_jamf_id(&device_id) {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    }
+    let jamf_client = device_jamf_client(&state, &tenant_id, &params).await?;
+    let mobile_device_provider = MobileDeviceProvider {
+        jamf_client,
+        staleness: state.staleness,
+    };
+    let mobile_device = mobile_device_provider
+        .fetch_mobile_device(&device_id)
+        .await
+        .map_err(jamf_error_response)?;
+    Ok(Json(mobile_device).into_response())
+}
+
+/// Devices assigned to exactly this email address
+/// Jamf treats `*` as a wildcard, which would turn the lookup of one person into a list of the fleet
+fn user_devices_query(email: &str) -> Result<ComputerInventoryQuery, &'static str> {
+    if !email.contains('@') {
+        return Err("Not an email address");
+    }
+    if email.contains('*') {
+        return Err("Email addresses can't contain wildcards");
+    }
+    Ok(ComputerInventoryQuery::default()
+        .filter(Filter::eq(ComputerField::UserAndLocationEmail, email)))
+}
+
+/// Every device assigned to a person, with its compliance, e.g. to tell them their laptop is out of date
+#[instrument(skip(state))]
+pub async fn user_devices(
+    State(state): State<AppState>,
+    Path(UserPath { tenant_id, email }): Path<UserPath>,
+    Query(params): Query<DeviceParams>,
+) -> Result<Response, Response> {
+    let query =
+        user_devices_query(&email).map_err(|e| (StatusCode::BAD_REQUEST, e).into_response())?;
+    let connection = resolve_connection(&state, &tenant_id, params.connection_id.as_deref())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    info!("Using Jamf connection {}", connection.id);
+
+    let jamf_client = state.jamf_clients.client_for(&connection).map_err(|e| {
+        error!("Failed to build Jamf transport: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
     })?;
-    let devices_output = DevicesOutput::from(inventory);
-    Ok(Json(devices_output))
+    let computer_provider = ComputerProvider {
+        jamf_client: JamfClient::Impl(jamf_client.clone()),
+        extension_attributes: state.extension_attributes.clone(),
+        staleness: state.staleness,
+    };
+    let mobile_device_provider = MobileDeviceProvider {
+        jamf_client: JamfClient::Impl(jamf_client),
+        staleness: state.staleness,
+    };
+    let devices_output = fetch_devices_or_stale(
+        &state,
+        &connection,
+        &computer_provider,
+        &mobile_device_provider,
+        query,
+    )
+    .await?;
+    Ok(Json(devices_output).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+    use http::{
+        header::{ACCEPT, RETRY_AFTER},
+        HeaderMap, StatusCode,
+    };
+    use reqwest::StatusCode as JamfStatusCode;
+
+    use crate::jamf::client::JamfClientError;
+
+    use super::{
+        extension_attribute_params, inventory_query, jamf_error_response, user_devices_query,
+        wants_ndjson, DevicesFormat, DevicesParams,
+    };
+
+    #[test]
+    fn ndjson_requested_by_format_or_accept() {
+        let params = |format| DevicesParams {
+            format,
+            ..Default::default()
+        };
+        let mut accept_ndjson = HeaderMap::new();
+        accept_ndjson.insert(ACCEPT, "application/x-ndjson".parse().unwrap());
+
+        assert!(!wants_ndjson(&params(None), &HeaderMap::new()));
+        assert!(wants_ndjson(&params(None), &accept_ndjson));
+        assert!(wants_ndjson(
+            &params(Some(DevicesFormat::Ndjson)),
+            &HeaderMap::new()
+        ));
+        // An explicit format wins over the Accept header
+        assert!(!wants_ndjson(
+            &params(Some(DevicesFormat::Json)),
+            &accept_ndjson
+        ));
+    }
+
+    #[test]
+    fn extension_attribute_filters_repeated() {
+        assert_eq!(
+            extension_attribute_params(Some(
+                "name=mac&extension_attribute=cost_center:R%26D%2C%20Sales&extension_attribute=edr_installed:2024-05-01"
+            )),
+            vec!["cost_center:R&D, Sales", "edr_installed:2024-05-01"]
+        );
+        assert!(extension_attribute_params(None).is_empty());
+    }
+
+    #[test]
+    fn jamf_errors_mapped_to_responses() {
+        let path = || "/api/v1/computers-inventory".to_string();
+        let cases = [
+            (
+                JamfClientError::AuthenticationFailed { path: path() },
+                StatusCode::FAILED_DEPENDENCY,
+            ),
+            (
+                JamfClientError::PermissionDenied { path: path() },
+                StatusCode::FAILED_DEPENDENCY,
+            ),
+            (
+                JamfClientError::NotFound { path: path() },
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                JamfClientError::ServerError {
+                    path: path(),
+                    status: JamfStatusCode::SERVICE_UNAVAILABLE,
+                    retry_after: None,
+                },
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                JamfClientError::MalformedPayload {
+                    path: path(),
+                    error: "expected value".to_string(),
+                    snippet: "<html>".to_string(),
+                },
+                StatusCode::BAD_GATEWAY,
+            ),
+        ];
+        for (error, status) in cases {
+            assert_eq!(jamf_error_response(error).status(), status);
+        }
+
+        let rate_limited = jamf_error_response(JamfClientError::RateLimited {
+            path: path(),
+            retry_after: Some(Duration::from_secs(30)),
+        });
+        assert_eq!(rate_limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rate_limited.headers()[RETRY_AFTER], "30");
+
+        let circuit_open = jamf_error_response(JamfClientError::CircuitOpen {
+            path: path(),
+            retry_after: Duration::from_millis(4200),
+        });
+        assert_eq!(circuit_open.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(circuit_open.headers()[RETRY_AFTER], "5");
+    }
+
+    #[test]
+    fn filter_params_pushed_to_jamf() {
+        let params = DevicesParams {
+            name: Some("peter’s *".to_string()),
+            os_version: Some("14.4, 14.5".to_string()),
+            reported_after: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            sort: Some("general.reportDate:desc".to_string()),
+            ..Default::default()
+        };
+        let query = inventory_query(&params).unwrap();
+        assert_eq!(
+            query.filter.unwrap().to_string(),
+            "general.name==\"peter’s *\";operatingSystem.version=in=(14.4,14.5);\
+             general.reportDate>2024-01-01T00:00:00Z"
+        );
+        assert_eq!(query.sort.len(), 1);
+
+        assert!(inventory_query(&DevicesParams::default())
+            .unwrap()
+            .filter
+            .is_none());
+        let unknown_sort = DevicesParams {
+            sort: Some("general.password".to_string()),
+            ..Default::default()
+        };
+        assert!(inventory_query(&unknown_sort).is_err());
+    }
+
+    #[test]
+    fn user_devices_only_for_exact_email() {
+        let query = user_devices_query("peter@example.com").unwrap();
+        assert_eq!(
+            query.filter.unwrap().to_string(),
+            "userAndLocation.email==peter@example.com"
+        );
+        assert!(user_devices_query("peter").is_err());
+        assert!(user_devices_query("*@*").is_err());
+        assert!(user_devices_query("peter*@example.com").is_err());
+    }
 }
@@ -1,3 +1,35 @@
+use serde::Deserialize;
+
+pub mod api_keys;
+pub mod applications;
+pub mod connections;
 pub mod credentials;
 pub mod devices;
 pub mod hello;
+
+/// Path parameters for routes nested under `/api/tenants/:tenant_id`
+#[derive(Deserialize, Debug)]
+pub struct TenantPath {
+    pub tenant_id: String,
+}
+
+/// Path parameters for routes addressing a single connection of a tenant
+#[derive(Deserialize, Debug)]
+pub struct ConnectionPath {
+    pub tenant_id: String,
+    pub connection_id: String,
+}
+
+/// Path parameters for routes addressing a single device of a tenant
+#[derive(Deserialize, Debug)]
+pub struct DevicePath {
+    pub tenant_id: String,
+    pub device_id: String,
+}
+
+/// Path parameters for routes addressing a single person of a tenant
+#[derive(Deserialize, Debug)]
+pub struct UserPath {
+    pub tenant_id: String,
+    pub email: String,
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use crate::{
+    api_auth::ApiAuth,
+    jamf::{
+        applications::{ApplicationIndex, ApplicationSearchConfig},
+        cache::DevicesCache,
+        extension_attributes::ExtensionAttributesConfig,
+        posture::StalenessConfig,
+        registry::JamfClientRegistry,
+    },
+    store::{api_keys::ApiKeyStore, connections::ConnectionStore},
+};
+
+/// Shared state handed to every route handler
+#[derive(Clone)]
+pub struct AppState {
+    pub connections: Arc<ConnectionStore>,
+    /// Jamf clients per tenant connection, kept across requests so tokens are reused
+    pub jamf_clients: Arc<JamfClientRegistry>,
+    /// Last device lists fetched from Jamf, served while Jamf is unavailable
+    pub devices_cache: Arc<DevicesCache>,
+    /// Extension attributes shown on computers, configured by the operator
+    pub extension_attributes: Arc<ExtensionAttributesConfig>,
+    /// Devices that haven't checked in for longer than this are flagged as stale
+    pub staleness: StalenessConfig,
+    /// Application searches scan the whole fleet, this bounds how long one may take
+    pub application_search: ApplicationSearchConfig,
+    /// Last app scan of each connection, searched until it's older than the configured max age
+    pub application_index: Arc<ApplicationIndex>,
+    /// API keys for calling our own API
+    pub api_keys: Arc<ApiKeyStore>,
+    pub api_auth: Arc<ApiAuth>,
+}
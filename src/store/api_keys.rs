@@ -0,0 +1,268 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::api_auth::Scope;
+
+use super::{connections::StoreError, write_atomically};
+
+/// Every API key starts with this, so they're easy to tell apart from JWTs and to spot in leaked logs
+pub(crate) static API_KEY_PREFIX: &str = "zsk_";
+
+/// Everything about an API key except the key itself, which is only returned once when minting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ApiKeySummary {
+    pub key_id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Tenant the key is restricted to, `None` allows every tenant
+    pub tenant_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeySummary {
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires| expires > now)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StoredApiKey {
+    #[serde(flatten)]
+    summary: ApiKeySummary,
+    // Only a hash of the secret part is kept, a leaked store can't be used to call the API
+    secret_hash: String,
+}
+
+fn hash_secret(secret: &str) -> String {
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// API keys for our own API, formatted as `zsk_<key id>_<secret>`
+pub struct ApiKeyStore {
+    // Where the keys are persisted, `None` keeps them in memory only
+    path: Option<PathBuf>,
+    keys: RwLock<HashMap<String, StoredApiKey>>,
+}
+
+impl ApiKeyStore {
+    pub async fn open(path: Option<PathBuf>) -> Result<Self, StoreError> {
+        let mut keys = HashMap::new();
+        if let Some(path) = path.as_ref().filter(|p| p.exists()) {
+            let stored: Vec<StoredApiKey> = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+            for key in stored {
+                keys.insert(key.summary.key_id.clone(), key);
+            }
+            info!("Loaded {} API keys from {}", keys.len(), path.display());
+        }
+        Ok(Self {
+            path,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    async fn persist(&self, keys: &HashMap<String, StoredApiKey>) -> Result<(), StoreError> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut stored = keys.values().collect::<Vec<_>>();
+        stored.sort_by_key(|k| k.summary.created_at);
+        Ok(write_atomically(path, &serde_json::to_vec_pretty(&stored)?).await?)
+    }
+
+    /// Create a new API key, returns the key itself which can't be recovered afterwards
+    pub async fn mint(
+        &self,
+        name: String,
+        scopes: Vec<Scope>,
+        tenant_id: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(ApiKeySummary, String), StoreError> {
+        let key_id = Uuid::new_v4().simple().to_string();
+        let mut secret_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut secret_bytes);
+        let secret = URL_SAFE_NO_PAD.encode(secret_bytes);
+
+        let summary = ApiKeySummary {
+            key_id: key_id.clone(),
+            name,
+            scopes,
+            tenant_id,
+            created_at: Utc::now(),
+            expires_at,
+            revoked_at: None,
+        };
+        let mut keys = self.keys.write().await;
+        // Only keep the change once it's on disk, so memory never has keys the file doesn't
+        let mut updated = keys.clone();
+        updated.insert(
+            key_id.clone(),
+            StoredApiKey {
+                summary: summary.clone(),
+                secret_hash: hash_secret(&secret),
+            },
+        );
+        self.persist(&updated).await?;
+        *keys = updated;
+        Ok((summary, format!("{}{}_{}", API_KEY_PREFIX, key_id, secret)))
+    }
+
+    /// Check a presented API key, returns the key if it exists and is neither revoked nor expired
+    pub async fn verify(&self, presented: &str) -> Option<ApiKeySummary> {
+        let (key_id, secret) = presented.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+        let keys = self.keys.read().await;
+        let key = keys.get(key_id)?;
+        // Constant time so a caller can't learn the hash byte by byte from response times
+        let matches = bool::from(
+            key.secret_hash
+                .as_bytes()
+                .ct_eq(hash_secret(secret).as_bytes()),
+        );
+        (matches && key.summary.is_active(Utc::now())).then(|| key.summary.clone())
+    }
+
+    pub async fn list(&self) -> Vec<ApiKeySummary> {
+        let mut summaries = self
+            .keys
+            .read()
+            .await
+            .values()
+            .map(|k| k.summary.clone())
+            .collect::<Vec<_>>();
+        summaries.sort_by_key(|s| s.created_at);
+        summaries
+    }
+
+    /// Revoke a key, it stays listed so there is a record of it having existed
+    pub async fn revoke(&self, key_id: &str) -> Result<Option<ApiKeySummary>, StoreError> {
+        let mut keys = self.keys.write().await;
+        let mut updated = keys.clone();
+        let Some(key) = updated.get_mut(key_id) else {
+            return Ok(None);
+        };
+        key.summary.revoked_at.get_or_insert_with(Utc::now);
+        let summary = key.summary.clone();
+        self.persist(&updated).await?;
+        *keys = updated;
+        Ok(Some(summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use crate::api_auth::Scope;
+
+    use super::ApiKeyStore;
+
+    #[tokio::test]
+    async fn mint_and_verify_key() {
+        let store = ApiKeyStore::open(None).await.unwrap();
+        let (summary, key) = store
+            .mint(
+                "dashboard".to_string(),
+                vec![Scope::DevicesRead],
+                Some("acme".to_string()),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(key.starts_with("zsk_"));
+
+        let verified = store.verify(&key).await.expect("Should be valid");
+        assert_eq!(verified, summary);
+        assert!(store.verify(&format!("{}x", key)).await.is_none());
+        assert!(store.verify("zsk_unknown_secret").await.is_none());
+        assert!(store.verify("not a key").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn revoked_and_expired_keys_rejected() {
+        let store = ApiKeyStore::open(None).await.unwrap();
+        let (summary, key) = store
+            .mint("revoked".to_string(), vec![Scope::Admin], None, None)
+            .await
+            .unwrap();
+        let revoked = store.revoke(&summary.key_id).await.unwrap().unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert!(store.verify(&key).await.is_none());
+        assert!(store.revoke("unknown").await.unwrap().is_none());
+
+        let (_, expired_key) = store
+            .mint(
+                "expired".to_string(),
+                vec![Scope::Admin],
+                None,
+                Some(Utc::now() - Duration::minutes(1)),
+            )
+            .await
+            .unwrap();
+        assert!(store.verify(&expired_key).await.is_none());
+        assert_eq!(store.list().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn persisted_keys_only_store_hashes() {
+        let path = std::env::temp_dir().join(format!("api-keys-{}.json", Uuid::new_v4()));
+        let store = ApiKeyStore::open(Some(path.clone())).await.unwrap();
+        let (_, key) = store
+            .mint(
+                "dashboard".to_string(),
+                vec![Scope::DevicesRead],
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        // The secret is base64url and can contain underscores itself, so only split off the prefix and key id
+        let (_, secret) = key.strip_prefix("zsk_").unwrap().split_once('_').unwrap();
+        assert!(!contents.contains(secret));
+
+        let reopened = ApiKeyStore::open(Some(path.clone())).await.unwrap();
+        assert!(reopened.verify(&key).await.is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_persist_leaves_keys_unchanged() {
+        let dir = std::env::temp_dir().join(format!("api-keys-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let store = ApiKeyStore::open(Some(dir.join("api_keys.json")))
+            .await
+            .unwrap();
+        let (summary, key) = store
+            .mint(
+                "dashboard".to_string(),
+                vec![Scope::DevicesRead],
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Nothing can be written once the directory is gone
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(store
+            .mint("ci".to_string(), vec![Scope::DevicesRead], None, None)
+            .await
+            .is_err());
+        assert_eq!(store.list().await.len(), 1);
+
+        assert!(store.revoke(&summary.key_id).await.is_err());
+        assert!(store.verify(&key).await.is_some());
+    }
+}
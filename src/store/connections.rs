@@ -0,0 +1,622 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::jamf::{auth::JamfCredentials, transport::TransportConfig};
+
+use super::{
+    encryption::{EncryptionError, EnvelopeKey, SealedSecret},
+    write_atomically,
+};
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Failed to encrypt or decrypt stored credentials: {0}")]
+    Encryption(#[from] EncryptionError),
+    #[error("Failed to (de)serialize stored credentials: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Failed to read or write the credential store: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A Jamf instance together with the decrypted credentials we use to talk to it
+#[derive(Clone, Debug)]
+pub(crate) struct JamfConnection {
+    pub(crate) id: String,
+    pub(crate) tenant_id: String,
+    pub(crate) jamf_url: String,
+    pub(crate) transport: TransportConfig,
+    pub(crate) credentials: JamfCredentials,
+}
+
+/// Everything about a stored connection except the secret, safe to hand back to callers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionSummary {
+    pub connection_id: String,
+    pub tenant_id: String,
+    pub url: String,
+    /// Username or API client id the connection authenticates as
+    pub principal: String,
+    pub auth_type: String,
+    /// How we reach the instance, kept in plain text since it never holds secrets
+    #[serde(default)]
+    pub transport: TransportConfig,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What actually gets kept in memory and written to disk, the credentials only ever exist encrypted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StoredConnection {
+    #[serde(flatten)]
+    summary: ConnectionSummary,
+    secret: SealedSecret,
+}
+
+/// Server side storage for Jamf connections, callers only ever get the opaque connection id back
+/// Every connection belongs to a tenant and is only visible to lookups for that tenant
+/// Credentials are encrypted with a per connection data key which is in turn wrapped by the envelope key
+pub struct ConnectionStore {
+    envelope_key: RwLock<EnvelopeKey>,
+    // Where the encrypted connections are persisted, `None` keeps them in memory only
+    path: Option<PathBuf>,
+    connections: RwLock<HashMap<String, StoredConnection>>,
+}
+
+// Secrets are bound to their tenant and connection, so a secret copied onto another record won't decrypt
+fn associated_data(tenant_id: &str, connection_id: &str) -> Vec<u8> {
+    format!("{}/{}", tenant_id, connection_id).into_bytes()
+}
+
+impl StoredConnection {
+    fn associated_data(&self) -> Vec<u8> {
+        associated_data(&self.summary.tenant_id, &self.summary.connection_id)
+    }
+
+    fn open(&self, envelope_key: &EnvelopeKey) -> Result<JamfConnection, StoreError> {
+        let plaintext = self.secret.open(envelope_key, &self.associated_data())?;
+        Ok(JamfConnection {
+            id: self.summary.connection_id.clone(),
+            tenant_id: self.summary.tenant_id.clone(),
+            jamf_url: self.summary.url.clone(),
+            transport: self.summary.transport.clone(),
+            credentials: serde_json::from_slice(&plaintext)?,
+        })
+    }
+}
+
+fn seal_credentials(
+    envelope_key: &EnvelopeKey,
+    tenant_id: &str,
+    connection_id: &str,
+    credentials: &JamfCredentials,
+) -> Result<SealedSecret, StoreError> {
+    Ok(SealedSecret::seal(
+        envelope_key,
+        &serde_json::to_vec(credentials)?,
+        &associated_data(tenant_id, connection_id),
+    )?)
+}
+
+impl ConnectionStore {
+    /// Open the store, loading any connections previously persisted at `path`
+    /// Every stored secret is checked against the envelope key so a wrong key fails at startup
+    pub async fn open(
+        envelope_key: EnvelopeKey,
+        path: Option<PathBuf>,
+    ) -> Result<Self, StoreError> {
+        let mut connections = HashMap::new();
+        if let Some(path) = path.as_ref().filter(|p| p.exists()) {
+            let stored: Vec<StoredConnection> =
+                serde_json::from_slice(&tokio::fs::read(path).await?)?;
+            for connection in stored {
+                connection.open(&envelope_key)?;
+                connections.insert(connection.summary.connection_id.clone(), connection);
+            }
+            info!(
+                "Loaded {} Jamf connections from {}",
+                connections.len(),
+                path.display()
+            );
+        }
+        Ok(Self {
+            envelope_key: RwLock::new(envelope_key),
+            path,
+            connections: RwLock::new(connections),
+        })
+    }
+
+    // Write every connection to disk, callers must hold the write lock so writes don't interleave
+    async fn persist(
+        &self,
+        connections: &HashMap<String, StoredConnection>,
+    ) -> Result<(), StoreError> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut stored = connections.values().collect::<Vec<_>>();
+        stored.sort_by_key(|c| c.summary.created_at);
+        let contents = serde_json::to_vec_pretty(&stored)?;
+
+        Ok(write_atomically(path, &contents).await?)
+    }
+
+    /// Store a new connection for a tenant and return it with its newly assigned id
+    pub(crate) async fn insert(
+        &self,
+        tenant_id: &str,
+        jamf_url: String,
+        transport: TransportConfig,
+        credentials: JamfCredentials,
+    ) -> Result<JamfConnection, StoreError> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let secret = seal_credentials(
+            &*self.envelope_key.read().await,
+            tenant_id,
+            &id,
+            &credentials,
+        )?;
+
+        let mut connections = self.connections.write().await;
+        // Only keep the change once it's on disk, so memory never has connections the file doesn't
+        let mut updated = connections.clone();
+        updated.insert(
+            id.clone(),
+            StoredConnection {
+                summary: ConnectionSummary {
+                    connection_id: id.clone(),
+                    tenant_id: tenant_id.to_string(),
+                    url: jamf_url.clone(),
+                    principal: credentials.principal().to_string(),
+                    auth_type: credentials.auth_type().to_string(),
+                    transport: transport.clone(),
+                    created_at: now,
+                    updated_at: now,
+                },
+                secret,
+            },
+        );
+        self.persist(&updated).await?;
+        *connections = updated;
+        Ok(JamfConnection {
+            id,
+            tenant_id: tenant_id.to_string(),
+            jamf_url,
+            transport,
+            credentials,
+        })
+    }
+
+    /// Get a tenant's connection with its credentials decrypted
+    /// Connections belonging to other tenants are reported as missing, the same as unknown ids
+    pub(crate) async fn get(
+        &self,
+        tenant_id: &str,
+        id: &str,
+    ) -> Result<Option<JamfConnection>, StoreError> {
+        let envelope_key = self.envelope_key.read().await;
+        self.connections
+            .read()
+            .await
+            .get(id)
+            .filter(|connection| connection.summary.tenant_id == tenant_id)
+            .map(|connection| connection.open(&envelope_key))
+            .transpose()
+    }
+
+    pub async fn list(&self, tenant_id: &str) -> Vec<ConnectionSummary> {
+        let mut summaries = self
+            .connections
+            .read()
+            .await
+            .values()
+            .filter(|c| c.summary.tenant_id == tenant_id)
+            .map(|c| c.summary.clone())
+            .collect::<Vec<_>>();
+        summaries.sort_by_key(|s| s.created_at);
+        summaries
+    }
+
+    /// Replace the credentials of an existing connection, the connection id stays the same
+    /// The transport is only replaced if a new one is given
+    /// Returns `None` if there is no connection with that id
+    pub(crate) async fn rotate(
+        &self,
+        tenant_id: &str,
+        id: &str,
+        transport: Option<TransportConfig>,
+        credentials: JamfCredentials,
+    ) -> Result<Option<JamfConnection>, StoreError> {
+        let envelope_key = self.envelope_key.read().await;
+        let mut connections = self.connections.write().await;
+        let mut updated = connections.clone();
+        let Some(connection) = updated
+            .get_mut(id)
+            .filter(|c| c.summary.tenant_id == tenant_id)
+        else {
+            return Ok(None);
+        };
+        connection.secret = seal_credentials(&envelope_key, tenant_id, id, &credentials)?;
+        connection.summary.principal = credentials.principal().to_string();
+        connection.summary.auth_type = credentials.auth_type().to_string();
+        if let Some(transport) = transport {
+            connection.summary.transport = transport;
+        }
+        connection.summary.updated_at = Utc::now();
+        let jamf_url = connection.summary.url.clone();
+        let transport = connection.summary.transport.clone();
+        self.persist(&updated).await?;
+        *connections = updated;
+        Ok(Some(JamfConnection {
+            id: id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            jamf_url,
+            transport,
+            credentials,
+        }))
+    }
+
+    /// Remove a connection and its encrypted secret, returns the removed connection if it existed
+    pub(crate) async fn delete(
+        &self,
+        tenant_id: &str,
+        id: &str,
+    ) -> Result<Option<JamfConnection>, StoreError> {
+        let envelope_key = self.envelope_key.read().await;
+        let mut connections = self.connections.write().await;
+        let Some(connection) = connections
+            .get(id)
+            .filter(|c| c.summary.tenant_id == tenant_id)
+        else {
+            return Ok(None);
+        };
+        // Decrypt first, the caller needs the credentials to revoke them and once the secret is
+        // gone there's no getting them back
+        let removed = connection.open(&envelope_key)?;
+        let mut updated = connections.clone();
+        updated.remove(id);
+        self.persist(&updated).await?;
+        *connections = updated;
+        Ok(Some(removed))
+    }
+
+    /// Re-wrap every data key with a new envelope key, the old key can be retired afterwards
+    pub async fn rotate_envelope_key(&self, new_key: EnvelopeKey) -> Result<(), StoreError> {
+        let mut envelope_key = self.envelope_key.write().await;
+        let mut connections = self.connections.write().await;
+        let rotated = connections
+            .iter()
+            .map(|(id, connection)| {
+                let secret = connection.secret.rewrap(
+                    &envelope_key,
+                    &new_key,
+                    &connection.associated_data(),
+                )?;
+                Ok((
+                    id.clone(),
+                    StoredConnection {
+                        summary: connection.summary.clone(),
+                        secret,
+                    },
+                ))
+            })
+            .collect::<Result<HashMap<_, _>, StoreError>>()?;
+        self.persist(&rotated).await?;
+        *connections = rotated;
+        *envelope_key = new_key;
+        info!("Rotated envelope key for {} connections", connections.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use uuid::Uuid;
+
+    use crate::{
+        jamf::{auth::JamfCredentials, transport::TransportConfig},
+        store::encryption::EnvelopeKey,
+    };
+
+    use super::{seal_credentials, ConnectionStore};
+
+    fn basic(password: &str) -> JamfCredentials {
+        JamfCredentials::Basic {
+            username: "user".to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    fn key(byte: u8) -> EnvelopeKey {
+        EnvelopeKey::from_base64(&STANDARD.encode([byte; 32])).unwrap()
+    }
+
+    fn temp_store_path() -> PathBuf {
+        std::env::temp_dir().join(format!("credential-store-{}.json", Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn insert_and_get_connection() {
+        let store = ConnectionStore::open(key(1), None).await.unwrap();
+        let connection = store
+            .insert(
+                "tenant",
+                "https://test.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("pass"),
+            )
+            .await
+            .unwrap();
+
+        let stored = store
+            .get("tenant", &connection.id)
+            .await
+            .unwrap()
+            .expect("Should be stored");
+        assert_eq!(stored.jamf_url, "https://test.jamfcloud.com");
+        assert_eq!(stored.credentials, basic("pass"));
+        assert!(store.get("tenant", "unknown").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn connection_ids_are_unique() {
+        let store = ConnectionStore::open(key(1), None).await.unwrap();
+        let url = "https://test.jamfcloud.com".to_string();
+        let first = store
+            .insert(
+                "tenant",
+                url.clone(),
+                TransportConfig::default(),
+                basic("pass"),
+            )
+            .await
+            .unwrap();
+        let second = store
+            .insert("tenant", url, TransportConfig::default(), basic("pass"))
+            .await
+            .unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(store.list("tenant").await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn connections_isolated_between_tenants() {
+        let store = ConnectionStore::open(key(1), None).await.unwrap();
+        let connection = store
+            .insert(
+                "tenant-a",
+                "https://a.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("pass"),
+            )
+            .await
+            .unwrap();
+
+        assert!(store
+            .get("tenant-b", &connection.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(store.list("tenant-b").await.is_empty());
+        assert!(store
+            .rotate("tenant-b", &connection.id, None, basic("new"))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(store
+            .delete("tenant-b", &connection.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Nothing tenant-b tried should have touched tenant-a's connection
+        let stored = store
+            .get("tenant-a", &connection.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.credentials, basic("pass"));
+        assert_eq!(store.list("tenant-a").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn persisted_store_is_encrypted() {
+        let path = temp_store_path();
+        let store = ConnectionStore::open(key(1), Some(path.clone()))
+            .await
+            .unwrap();
+        let connection = store
+            .insert(
+                "tenant",
+                "https://test.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("hunter2"),
+            )
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&connection.id));
+        assert!(!contents.contains("hunter2"));
+
+        let reopened = ConnectionStore::open(key(1), Some(path.clone()))
+            .await
+            .unwrap();
+        let stored = reopened
+            .get("tenant", &connection.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.credentials, basic("hunter2"));
+
+        // The wrong key is caught when opening, not on the first request
+        assert!(ConnectionStore::open(key(2), Some(path.clone()))
+            .await
+            .is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rotate_and_delete_connection() {
+        let store = ConnectionStore::open(key(1), None).await.unwrap();
+        let connection = store
+            .insert(
+                "tenant",
+                "https://test.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("old"),
+            )
+            .await
+            .unwrap();
+
+        let rotated = store
+            .rotate("tenant", &connection.id, None, basic("new"))
+            .await
+            .unwrap()
+            .expect("Should exist");
+        assert_eq!(rotated.id, connection.id);
+        let stored = store.get("tenant", &connection.id).await.unwrap().unwrap();
+        assert_eq!(stored.credentials, basic("new"));
+
+        // Rotating without a transport keeps the one the connection already has
+        let transport = TransportConfig {
+            proxy_url: Some("http://proxy.corp.example:3128".to_string()),
+            ..Default::default()
+        };
+        store
+            .rotate(
+                "tenant",
+                &connection.id,
+                Some(transport.clone()),
+                basic("new"),
+            )
+            .await
+            .unwrap();
+        store
+            .rotate("tenant", &connection.id, None, basic("new"))
+            .await
+            .unwrap();
+        let stored = store.get("tenant", &connection.id).await.unwrap().unwrap();
+        assert_eq!(stored.transport, transport);
+        assert!(store
+            .rotate("tenant", "unknown", None, basic("new"))
+            .await
+            .unwrap()
+            .is_none());
+
+        let deleted = store.delete("tenant", &connection.id).await.unwrap();
+        assert_eq!(deleted.unwrap().credentials, basic("new"));
+        assert!(store.get("tenant", &connection.id).await.unwrap().is_none());
+        assert!(store.list("tenant").await.is_empty());
+        assert!(store
+            .delete("tenant", &connection.id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_persist_leaves_connections_unchanged() {
+        let dir = std::env::temp_dir().join(format!("credential-store-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let store = ConnectionStore::open(key(1), Some(dir.join("connections.json")))
+            .await
+            .unwrap();
+        let connection = store
+            .insert(
+                "tenant",
+                "https://test.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("old"),
+            )
+            .await
+            .unwrap();
+
+        // Nothing can be written once the directory is gone
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(store
+            .insert(
+                "tenant",
+                "https://other.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("pass"),
+            )
+            .await
+            .is_err());
+        assert_eq!(store.list("tenant").await.len(), 1);
+
+        assert!(store
+            .rotate("tenant", &connection.id, None, basic("new"))
+            .await
+            .is_err());
+        let stored = store.get("tenant", &connection.id).await.unwrap().unwrap();
+        assert_eq!(stored.credentials, basic("old"));
+
+        assert!(store.delete("tenant", &connection.id).await.is_err());
+        assert!(store.get("tenant", &connection.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn undecryptable_connection_not_deleted() {
+        let store = ConnectionStore::open(key(1), None).await.unwrap();
+        let connection = store
+            .insert(
+                "tenant",
+                "https://test.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("pass"),
+            )
+            .await
+            .unwrap();
+        store
+            .connections
+            .write()
+            .await
+            .get_mut(&connection.id)
+            .unwrap()
+            .secret = seal_credentials(&key(2), "tenant", &connection.id, &basic("pass")).unwrap();
+
+        // Deleting it would lose the credentials that still need revoking
+        assert!(store.delete("tenant", &connection.id).await.is_err());
+        assert_eq!(store.list("tenant").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rotate_envelope_key() {
+        let path = temp_store_path();
+        let store = ConnectionStore::open(key(1), Some(path.clone()))
+            .await
+            .unwrap();
+        let connection = store
+            .insert(
+                "tenant",
+                "https://test.jamfcloud.com".to_string(),
+                TransportConfig::default(),
+                basic("pass"),
+            )
+            .await
+            .unwrap();
+
+        store.rotate_envelope_key(key(2)).await.unwrap();
+        let stored = store.get("tenant", &connection.id).await.unwrap().unwrap();
+        assert_eq!(stored.credentials, basic("pass"));
+
+        assert!(ConnectionStore::open(key(1), Some(path.clone()))
+            .await
+            .is_err());
+        let reopened = ConnectionStore::open(key(2), Some(path.clone()))
+            .await
+            .unwrap();
+        assert_eq!(reopened.list("tenant").await.len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// AES-GCM uses 96 bit nonces, they are stored in front of every ciphertext
+static NONCE_LEN: usize = 12;
+
+#[derive(Error, Debug)]
+pub enum EncryptionError {
+    #[error("Envelope key must be a base64 encoded 256 bit key")]
+    InvalidKey,
+    #[error("Stored secret is not valid base64: {0}")]
+    InvalidEncoding(#[from] base64::DecodeError),
+    // NOTE: aes_gcm deliberately doesn't say why decryption failed, it's either the wrong key or tampered data
+    #[error("Failed to encrypt or decrypt secret")]
+    Crypto,
+}
+
+/// Key encryption key supplied at startup, it never encrypts secrets directly, only the per secret data keys
+pub struct EnvelopeKey(Key<Aes256Gcm>);
+
+impl EnvelopeKey {
+    pub fn from_base64(encoded: &str) -> Result<Self, EncryptionError> {
+        let bytes = STANDARD.decode(encoded.trim())?;
+        if bytes.len() != 32 {
+            return Err(EncryptionError::InvalidKey);
+        }
+        Ok(Self(*Key::<Aes256Gcm>::from_slice(&bytes)))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng))
+    }
+}
+
+/// A secret encrypted with its own random data key, the data key is stored wrapped by the envelope key
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SealedSecret {
+    wrapped_key: String,
+    ciphertext: String,
+}
+
+fn encrypt(key: &Key<Aes256Gcm>, plaintext: &[u8], aad: &[u8]) -> Result<String, EncryptionError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| EncryptionError::Crypto)?;
+    Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+fn decrypt(key: &Key<Aes256Gcm>, encoded: &str, aad: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let bytes = STANDARD.decode(encoded)?;
+    if bytes.len() < NONCE_LEN {
+        return Err(EncryptionError::Crypto);
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    Aes256Gcm::new(key)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| EncryptionError::Crypto)
+}
+
+impl SealedSecret {
+    /// Encrypt a secret, `aad` binds the ciphertext to its record so it can't be swapped onto another one
+    pub(crate) fn seal(
+        envelope_key: &EnvelopeKey,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Self, EncryptionError> {
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        Ok(Self {
+            wrapped_key: encrypt(&envelope_key.0, data_key.as_slice(), aad)?,
+            ciphertext: encrypt(&data_key, plaintext, aad)?,
+        })
+    }
+
+    pub(crate) fn open(
+        &self,
+        envelope_key: &EnvelopeKey,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let data_key = self.unwrap_key(envelope_key, aad)?;
+        decrypt(&data_key, &self.ciphertext, aad)
+    }
+
+    /// Re-encrypt the data key with a new envelope key, the secret itself is left untouched
+    pub(crate) fn rewrap(
+        &self,
+        old_key: &EnvelopeKey,
+        new_key: &EnvelopeKey,
+        aad: &[u8],
+    ) -> Result<Self, EncryptionError> {
+        let data_key = self.unwrap_key(old_key, aad)?;
+        Ok(Self {
+            wrapped_key: encrypt(&new_key.0, data_key.as_slice(), aad)?,
+            ciphertext: self.ciphertext.clone(),
+        })
+    }
+
+    fn unwrap_key(
+        &self,
+        envelope_key: &EnvelopeKey,
+        aad: &[u8],
+    ) -> Result<Key<Aes256Gcm>, EncryptionError> {
+        let data_key = decrypt(&envelope_key.0, &self.wrapped_key, aad)?;
+        if data_key.len() != 32 {
+            return Err(EncryptionError::Crypto);
+        }
+        Ok(*Key::<Aes256Gcm>::from_slice(&data_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    use super::{EnvelopeKey, SealedSecret};
+
+    #[test]
+    fn seal_and_open() {
+        let key = EnvelopeKey::generate();
+        let sealed = SealedSecret::seal(&key, b"hunter2", b"connection").expect("Should seal");
+        assert!(!sealed.ciphertext.contains("hunter2"));
+        let opened = sealed.open(&key, b"connection").expect("Should open");
+        assert_eq!(opened, b"hunter2");
+    }
+
+    #[test]
+    fn open_with_wrong_key_or_aad_fails() {
+        let key = EnvelopeKey::generate();
+        let sealed = SealedSecret::seal(&key, b"hunter2", b"connection").expect("Should seal");
+        assert!(sealed
+            .open(&EnvelopeKey::generate(), b"connection")
+            .is_err());
+        assert!(sealed.open(&key, b"other connection").is_err());
+    }
+
+    #[test]
+    fn open_tampered_secret_fails() {
+        let key = EnvelopeKey::generate();
+        let mut sealed = SealedSecret::seal(&key, b"hunter2", b"connection").expect("Should seal");
+        let mut bytes = STANDARD.decode(&sealed.ciphertext).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        sealed.ciphertext = STANDARD.encode(bytes);
+        assert!(sealed.open(&key, b"connection").is_err());
+    }
+
+    #[test]
+    fn rewrap_with_new_key() {
+        let old_key = EnvelopeKey::generate();
+        let new_key = EnvelopeKey::generate();
+        let sealed = SealedSecret::seal(&old_key, b"hunter2", b"connection").expect("Should seal");
+        let rewrapped = sealed
+            .rewrap(&old_key, &new_key, b"connection")
+            .expect("Should rewrap");
+        assert_eq!(rewrapped.open(&new_key, b"connection").unwrap(), b"hunter2");
+        assert!(rewrapped.open(&old_key, b"connection").is_err());
+    }
+
+    #[test]
+    fn envelope_key_must_be_256_bits() {
+        assert!(EnvelopeKey::from_base64(&STANDARD.encode([7u8; 32])).is_ok());
+        assert!(EnvelopeKey::from_base64(&STANDARD.encode([7u8; 16])).is_err());
+        assert!(EnvelopeKey::from_base64("not base64!").is_err());
+    }
+}
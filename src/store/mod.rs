@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use tokio::io::AsyncWriteExt;
+
+pub mod api_keys;
+pub mod connections;
+pub mod encryption;
+
+/// Write a store file so that a crash never leaves a half written file behind
+/// The file is only readable by the user running the server
+pub(crate) async fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&tmp_path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&tmp_path, path).await
+}